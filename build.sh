@@ -3,5 +3,5 @@
 set -e
 
 cargo build --release
-cargo test
+cargo test --all-features
 cargo doc
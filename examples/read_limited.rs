@@ -6,17 +6,15 @@ fn main() -> LimitReaderResult<()> {
     let mut limit_reader = LimitReader::new();
     limit_reader.limit(24);
 
-    let output = limit_reader.read_limited("./README.md".into())?;
-    let bytes_read = output.bytes_read();
+    let (text, output) =
+        limit_reader.read_limited_text("./README.md".into(), TextBoundary::Char)?;
     println!(
-        "LimitReaderOutput: {}, Bytes remaining: {}",
+        "LimitReaderOutput: {}, Bytes remaining: {:?}, Bytes dropped at a UTF-8 boundary: {}",
         &output,
-        &output.bytes_remaining()
+        &output.bytes_remaining(),
+        &output.boundary_bytes_dropped()
     );
 
-    let data = limit_reader.buffer();
-    let text = String::from_utf8(data[..(bytes_read as usize)].to_vec())?;
-
     println!("First line from README: {}", &text);
 
     Ok(())
@@ -0,0 +1,243 @@
+//! Limit-reads files or stdin, writing the accepted bytes to stdout.
+//!
+//! ```text
+//! $ curl -s https://example.com/upload.gz | limit-reader --limit 10M --decode auto > upload
+//! $ limit-reader --decode auto --max-ratio 100 --report json suspicious/*
+//! ```
+
+use better_limit_reader::prelude::*;
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Limit-read files or stdin, protecting against decompression bombs.
+#[derive(Debug, Parser)]
+#[command(name = "limit-reader", version)]
+struct Args {
+    /// Files to read; reads stdin if none are given, or for `-`.
+    files: Vec<PathBuf>,
+
+    /// Maximum decoded bytes accepted from each input, with an optional `k`, `m` or `g` suffix.
+    #[arg(short, long, default_value = "1m", value_parser = parse_size)]
+    limit: u64,
+
+    /// Decode the input before limiting it; `auto` detects Zlib or Gzip from the first bytes.
+    #[arg(short, long)]
+    decode: Option<Decode>,
+
+    /// Fail on inputs exceeding the limit, the default.
+    #[arg(long, conflicts_with = "truncate")]
+    strict: bool,
+
+    /// Silently truncate inputs at the limit instead of failing.
+    #[arg(long)]
+    truncate: bool,
+
+    /// Fail once the decoded bytes exceed this many times the compressed bytes.
+    #[arg(long, value_name = "RATIO")]
+    max_ratio: Option<f64>,
+
+    /// Print statistics for each input, one per line, instead of the accepted bytes.
+    #[arg(long, value_name = "FORMAT")]
+    report: Option<Report>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Decode {
+    Zlib,
+    Gzip,
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Report {
+    Json,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let inputs = if args.files.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        args.files.clone()
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut failed = false;
+    for path in &inputs {
+        let result = read_input(&args, path, &mut out);
+        if let Some(Report::Json) = args.report {
+            let line = json_report(path, &result);
+            if writeln!(out, "{line}").is_err() {
+                return ExitCode::FAILURE;
+            }
+        }
+        if let Err(err) = result {
+            eprintln!("limit-reader: {}: {err}", path.display());
+            failed = true;
+        }
+    }
+    if out.flush().is_err() || failed {
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+// Limit-reads the input at `path`, writing the accepted bytes to `out` unless reporting.
+fn read_input(
+    args: &Args,
+    path: &Path,
+    out: &mut dyn Write,
+) -> LimitReaderResult<LimitReaderOutput> {
+    let source: Box<dyn Read> = if path.as_os_str() == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(path)?)
+    };
+
+    read_input_from(args, source, out)
+}
+
+// Limit-reads `source` as configured by `args`, writing the accepted bytes to `out` unless reporting.
+fn read_input_from(
+    args: &Args,
+    source: impl Read,
+    out: &mut dyn Write,
+) -> LimitReaderResult<LimitReaderOutput> {
+    let mut limit_reader = LimitReader::new();
+    limit_reader.limit(args.limit);
+    match args.decode {
+        Some(Decode::Zlib) => limit_reader.enable_decode_zlib(),
+        Some(Decode::Gzip) => limit_reader.enable_decode_gzip(),
+        Some(Decode::Auto) => limit_reader.enable_decode_auto(),
+        None => &mut limit_reader,
+    };
+    if let Some(ratio) = args.max_ratio {
+        limit_reader.max_ratio(ratio);
+    }
+
+    let mut sink = io::sink();
+    let mut out = if args.report.is_some() {
+        &mut sink
+    } else {
+        out
+    };
+    if args.truncate {
+        limit_reader.copy_limited(source, &mut out)
+    } else {
+        limit_reader.copy(source, &mut out)
+    }
+}
+
+// Statistics of a read, or the error it failed with, as printed by `--report json`.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum JsonReport<'a> {
+    Read {
+        path: String,
+        #[serde(flatten)]
+        output: &'a LimitReaderOutput,
+    },
+    Failed {
+        path: String,
+        error: String,
+        kind: String,
+    },
+}
+
+fn json_report(path: &Path, result: &LimitReaderResult<LimitReaderOutput>) -> String {
+    let path = path.display().to_string();
+    let report = match result {
+        Ok(output) => JsonReport::Read { path, output },
+        Err(err) => JsonReport::Failed {
+            path,
+            error: err.to_string(),
+            kind: err.kind().to_string(),
+        },
+    };
+
+    serde_json::to_string(&report).expect("statistics serialize to JSON")
+}
+
+// Parses a size such as `512`, `64k`, `10M` or `1g`, in binary units.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let (digits, shift) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 30),
+        _ => (s, 0),
+    };
+    let size: u64 = digits.parse().map_err(|_| format!("invalid size `{s}`"))?;
+
+    size.checked_mul(1 << shift)
+        .ok_or_else(|| format!("size `{s}` is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("64k"), Ok(64 << 10));
+        assert_eq!(parse_size("10M"), Ok(10 << 20));
+        assert_eq!(parse_size("1g"), Ok(1 << 30));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("k").is_err());
+        assert!(parse_size("1.5m").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("17179869184g").is_err());
+    }
+
+    #[test]
+    fn strict_mode_is_the_default() {
+        for argv in [
+            &["limit-reader", "--limit", "8"][..],
+            &["limit-reader", "--limit", "8", "--strict"],
+        ] {
+            let args = Args::parse_from(argv);
+            let err = read_input_from(&args, &b"Mike was here."[..], &mut Vec::new()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        }
+
+        let err = Args::try_parse_from(["limit-reader", "--strict", "--truncate"]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn reports_reads_as_json() {
+        let args = Args::parse_from([
+            "limit-reader",
+            "--limit",
+            "8",
+            "--truncate",
+            "--report",
+            "json",
+        ]);
+        let path = Path::new("-");
+        let mut out = Vec::new();
+        let output = read_input_from(&args, &b"Mike was here."[..], &mut out);
+        assert!(out.is_empty());
+
+        let report: Value = serde_json::from_str(&json_report(path, &output)).unwrap();
+        assert_eq!(report["path"], "-");
+        assert_eq!(report["bytes_read"], 8);
+        assert_eq!(report["truncated"], true);
+        assert_eq!(report["encoding"], "identity");
+
+        let args = Args::parse_from(["limit-reader", "--limit", "8"]);
+        let result = read_input_from(&args, &b"Mike was here."[..], &mut out);
+        let report: Value = serde_json::from_str(&json_report(path, &result)).unwrap();
+        assert_eq!(report["path"], "-");
+        assert_eq!(report["kind"], result.unwrap_err().kind().to_string());
+        assert!(report["error"].is_string());
+        assert!(report.get("bytes_read").is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::error::{ErrorKind, LimitReaderError};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A byte budget shared by several readers, e.g. to enforce a total across all files of a request.  Clones draw from the same budget, concurrently.
+///
+/// Each reader keeps its own limit as well; the reader whose bytes cross the budget fails with [`ErrorKind::BudgetExceeded`].
+#[derive(Debug, Clone)]
+pub struct SharedBudget(Arc<Budget>);
+
+#[derive(Debug)]
+struct Budget {
+    name: String,
+    limit: u64,
+    used: AtomicU64,
+}
+
+impl SharedBudget {
+    /// Create a budget of `limit` bytes, named in errors by `name`.
+    pub fn new(name: impl Into<String>, limit: u64) -> Self {
+        Self(Arc::new(Budget {
+            name: name.into(),
+            limit,
+            used: AtomicU64::new(0),
+        }))
+    }
+
+    /// The name of the budget.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// The total bytes allowed.
+    #[must_use]
+    pub fn limit(&self) -> u64 {
+        self.0.limit
+    }
+
+    /// Bytes drawn so far.
+    #[must_use]
+    pub fn used(&self) -> u64 {
+        self.0.used.load(Ordering::Relaxed)
+    }
+
+    /// Bytes that may still be drawn.
+    #[must_use]
+    pub fn remaining(&self) -> u64 {
+        self.limit() - self.used()
+    }
+
+    /// Draw `bytes` from the budget, or fail without drawing any if that would exceed it.
+    pub(crate) fn charge(&self, bytes: u64) -> io::Result<()> {
+        let limit = self.0.limit;
+        self.0
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|&total| total <= limit)
+            })
+            .map_err(|_| {
+                LimitReaderError::new(
+                    ErrorKind::BudgetExceeded,
+                    BudgetExceededError {
+                        budget: self.0.name.clone(),
+                        limit,
+                    },
+                )
+            })?;
+
+        Ok(())
+    }
+}
+
+/// Structured error for [`ErrorKind::BudgetExceeded`], naming the budget that was exceeded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetExceededError {
+    pub(crate) budget: String,
+    pub(crate) limit: u64,
+}
+
+impl BudgetExceededError {
+    /// The name of the budget.
+    #[must_use]
+    pub fn budget(&self) -> &str {
+        &self.budget
+    }
+
+    /// The total bytes allowed by the budget.
+    #[must_use]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+}
+
+impl Display for BudgetExceededError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "budget `{}` of {} bytes exceeded",
+            self.budget, self.limit
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceededError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LimitReader, LimitReaderFallible};
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::thread;
+    use tempfile::tempdir;
+
+    #[test]
+    fn concurrent_readers_share_budget() {
+        let budget = SharedBudget::new("request", 1000);
+
+        let handles: Vec<_> = (0..12)
+            .map(|_| {
+                let budget = budget.clone();
+                thread::spawn(move || {
+                    let mut reader = LimitReaderFallible::new([b'x'; 100].as_slice(), 100);
+                    reader.shared_budget(budget);
+                    reader.read_to_end(&mut Vec::new()).is_ok()
+                })
+            })
+            .collect();
+        let succeeded = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .filter(|&ok| ok)
+            .count();
+
+        assert_eq!(succeeded, 10);
+        assert_eq!(budget.used(), 1000);
+        assert_eq!(budget.remaining(), 0);
+    }
+
+    #[test]
+    fn charges_only_bytes_delivered() {
+        let budget = SharedBudget::new("request", 1 << 20);
+        let data = vec![b'x'; 100_000];
+        let mut reader = LimitReaderFallible::new(data.as_slice(), 1 << 20);
+        reader.shared_budget(budget.clone());
+        let mut buf = [0; 100];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(budget.used(), 100);
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test_output.txt");
+        File::create(&file_path).unwrap().write_all(&data).unwrap();
+
+        let budget = SharedBudget::new("request", 1 << 20);
+        let mut limit_reader = LimitReader::new();
+        limit_reader.limit(1 << 20).shared_budget(budget.clone());
+        let output = limit_reader.read_limited(file_path).unwrap();
+        assert_eq!(output.bytes_read(), LimitReader::DEFAULT_BUF_SIZE as u64);
+        assert_eq!(budget.used(), output.bytes_read());
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn reader_crossing_budget_fails() {
+        let dir = tempdir().unwrap();
+
+        let file_path = dir.path().join("test_output.txt");
+        let mut file = File::create(&file_path).unwrap();
+        write!(file, "Mike was here. Briefly.").unwrap();
+
+        let budget = SharedBudget::new("uploads", 40);
+        let mut limit_reader = LimitReader::new();
+        limit_reader.shared_budget(budget.clone());
+
+        assert_eq!(limit_reader.read(file_path.clone()).unwrap(), 23);
+        let err = limit_reader.read(file_path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BudgetExceeded);
+        assert_eq!(
+            err.get_ref()
+                .downcast_ref::<BudgetExceededError>()
+                .unwrap()
+                .budget(),
+            "uploads"
+        );
+        assert_eq!(
+            err.to_string(),
+            "Error: budget `uploads` of 40 bytes exceeded".to_string()
+        );
+        assert_eq!(budget.used(), 23);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::LimitReaderOutput;
+
+/// A cloneable handle to cancel reads from another thread, e.g. when a client disconnects.  Readers check it before each read of the underlying source.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that has not been cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel all reads checking this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`CancellationToken::cancel`] was called.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancellationToken {
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self(flag)
+    }
+}
+
+/// Structured error for [`crate::ErrorKind::Cancelled`] raised by [`crate::LimitReader`], carrying the counts of the partial read.
+#[derive(Debug, Clone)]
+pub struct CancelledError {
+    pub(crate) output: LimitReaderOutput,
+}
+
+impl CancelledError {
+    /// What was read before the read was cancelled.
+    #[must_use]
+    pub fn output(&self) -> &LimitReaderOutput {
+        &self.output
+    }
+}
+
+impl std::fmt::Display for CancelledError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "read cancelled after {} bytes", self.output.bytes_read())
+    }
+}
+
+impl std::error::Error for CancelledError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Clock, ErrorKind, LimitReader, LimitReaderError, LimitReaderInfallible, ManualClock,
+    };
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    #[test]
+    fn adapter_fails_once_cancelled() {
+        let token = CancellationToken::new();
+        let mut reader = LimitReaderInfallible::new("Mike was here.".as_bytes(), 100);
+        reader.cancel_token(token.clone());
+
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        token.cancel();
+        // Buffered bytes are still returned; the next read of the source fails.
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        let err = LimitReaderError::from(err);
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+    }
+
+    // Cancels its token when a rate limit sleeps on it.
+    struct CancelOnSleep(CancellationToken, ManualClock);
+
+    impl Clock for CancelOnSleep {
+        fn now(&self) -> Instant {
+            self.1.now()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.0.cancel();
+            self.1.sleep(duration);
+        }
+    }
+
+    #[test]
+    fn limit_reader_reports_partial_counts() {
+        let dir = tempdir().unwrap();
+
+        let file_path = dir.path().join("test_output.txt");
+        let mut file = File::create(&file_path).unwrap();
+        write!(file, "Mike was here. Briefly.").unwrap();
+
+        let token = CancellationToken::from(Arc::new(AtomicBool::new(false)));
+        let mut limit_reader = LimitReader::new();
+        limit_reader
+            .cancel_token(token.clone())
+            .rate_limit(10, 10)
+            .clock(Arc::new(CancelOnSleep(token, ManualClock::new())));
+
+        let err = limit_reader.read_limited(file_path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        let output = err
+            .get_ref()
+            .downcast_ref::<CancelledError>()
+            .unwrap()
+            .output();
+        assert_eq!(output.source_size(), 23);
+        // Nothing is read once the first sleep cancelled the read, leaving at most the burst.
+        assert!(output.bytes_read() <= 10);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+}
@@ -0,0 +1,558 @@
+use crate::error::{ErrorKind, LimitReaderError};
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead, Read};
+
+/// Limits enforced on an HTTP/1.1 chunked body by a [`ChunkedDecoder`].
+///
+/// Chunk-size lines are limited to 4096 bytes and the trailer section to 8 KiB by default, as a peer could otherwise keep sending either without ever delivering data.  The other limits are disabled by default.
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_field_names)]
+pub struct ChunkedLimits {
+    max_line_len: usize,
+    max_chunks: Option<u64>,
+    max_decoded_len: Option<u64>,
+    max_trailer_len: Option<u64>,
+}
+
+impl Default for ChunkedLimits {
+    fn default() -> Self {
+        Self {
+            max_line_len: 4096,
+            max_chunks: None,
+            max_decoded_len: None,
+            max_trailer_len: Some(8 * 1024),
+        }
+    }
+}
+
+impl ChunkedLimits {
+    /// Create a new [`ChunkedLimits`] with the default limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit each chunk-size line, including chunk extensions, to `max_line_len` bytes, excluding the trailing CRLF.
+    pub fn max_line_len(&mut self, max_line_len: usize) -> &mut Self {
+        self.max_line_len = max_line_len;
+
+        self
+    }
+
+    /// Limit the body to `max_chunks` chunks, not counting the last, empty chunk.
+    pub fn max_chunks(&mut self, max_chunks: u64) -> &mut Self {
+        self.max_chunks = Some(max_chunks);
+
+        self
+    }
+
+    /// Limit the body to `max_decoded_len` bytes once decoded.  A chunk whose declared size would exceed it is rejected before it is read.
+    pub fn max_decoded_len(&mut self, max_decoded_len: u64) -> &mut Self {
+        self.max_decoded_len = Some(max_decoded_len);
+
+        self
+    }
+
+    /// Limit the trailer section to `max_trailer_len` bytes, including the CRLF ending each field and the section.
+    pub fn max_trailer_len(&mut self, max_trailer_len: u64) -> &mut Self {
+        self.max_trailer_len = Some(max_trailer_len);
+
+        self
+    }
+}
+
+/// The limit violated by a chunked body; see [`ChunkedLimitError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChunkedViolation {
+    /// A chunk-size line was longer than `max_line_len` bytes.
+    LineTooLong {
+        /// The configured maximum line length.
+        max_line_len: usize,
+    },
+    /// The body held more than `max_chunks` chunks.
+    TooManyChunks {
+        /// The configured maximum number of chunks.
+        max_chunks: u64,
+    },
+    /// The chunks declared more than `max_decoded_len` bytes in total.
+    BodyTooLong {
+        /// The configured maximum decoded length.
+        max_decoded_len: u64,
+    },
+    /// The trailer section was longer than `max_trailer_len` bytes.
+    TrailerTooLong {
+        /// The configured maximum trailer length.
+        max_trailer_len: u64,
+    },
+    /// The body is not validly chunked, e.g. because it was cut short.
+    Malformed,
+}
+
+/// Structured error raised by a [`ChunkedDecoder`], locating the offending part of the body by its offset.
+///
+/// Retrieve it from a [`LimitReaderError`] of kind [`ErrorKind::ChunkedLimitExceeded`], or [`ErrorKind::Deserialize`] for [`ChunkedViolation::Malformed`], via `LimitReaderError::get_ref()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkedLimitError {
+    offset: u64,
+    violation: ChunkedViolation,
+}
+
+impl ChunkedLimitError {
+    /// Offset of the offending chunk-size line, or byte, in the chunked body as received.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The limit that was violated.
+    #[must_use]
+    pub fn violation(&self) -> ChunkedViolation {
+        self.violation
+    }
+}
+
+impl Display for ChunkedLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "chunked body at offset {} ", self.offset)?;
+        match self.violation {
+            ChunkedViolation::LineTooLong { max_line_len } => write!(
+                f,
+                "exceeds the maximum chunk-size line length of {max_line_len} bytes"
+            ),
+            ChunkedViolation::TooManyChunks { max_chunks } => {
+                write!(f, "exceeds the maximum of {max_chunks} chunks")
+            }
+            ChunkedViolation::BodyTooLong { max_decoded_len } => write!(
+                f,
+                "exceeds the maximum decoded length of {max_decoded_len} bytes"
+            ),
+            ChunkedViolation::TrailerTooLong { max_trailer_len } => write!(
+                f,
+                "exceeds the maximum trailer length of {max_trailer_len} bytes"
+            ),
+            ChunkedViolation::Malformed => f.write_str("is malformed"),
+        }
+    }
+}
+
+impl std::error::Error for ChunkedLimitError {}
+
+// Where the decoder is within the body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Size,
+    Data { remaining: u64 },
+    DataEnd { cr: bool },
+    // Within a trailer line of `len` bytes so far, the last of which was a CR if `cr`.
+    Trailer { len: u64, cr: bool },
+    Done,
+}
+
+/// Decodes an HTTP/1.1 chunked body (RFC 9112, section 7.1) read from a [`BufRead`], while enforcing [`ChunkedLimits`].
+///
+/// Chunk extensions and trailer fields are discarded.  Lines must end with CRLF, and the chunk size may only be followed by whitespace ahead of chunk extensions.  Reading stops after the trailer section, leaving any bytes that follow the body in the underlying reader.  Violations fail the read with an [`io::Error`] wrapping a [`LimitReaderError`], which the methods of [`crate::LimitReader`] unwrap; see [`crate::LimitReader::decode_chunked`].
+pub struct ChunkedDecoder<R> {
+    reader: R,
+    limits: ChunkedLimits,
+    state: State,
+    line: Vec<u8>,
+    offset: u64,
+    chunks: u64,
+    decoded: u64,
+    trailer_len: u64,
+}
+
+impl<R: BufRead> ChunkedDecoder<R> {
+    /// Wrap `reader`, decoding the chunked body read from it while enforcing `limits`.
+    pub fn new(reader: R, limits: &ChunkedLimits) -> Self {
+        Self {
+            reader,
+            limits: limits.clone(),
+            state: State::Size,
+            line: Vec::new(),
+            offset: 0,
+            chunks: 0,
+            decoded: 0,
+            trailer_len: 0,
+        }
+    }
+
+    /// Number of chunks decoded so far, not counting the last, empty chunk.
+    #[must_use]
+    pub fn chunk_count(&self) -> u64 {
+        self.chunks
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.offset += amt as u64;
+    }
+
+    // Reads a chunk-size line, moving on to its data or to the trailer section.
+    fn read_size(&mut self) -> io::Result<()> {
+        self.line.clear();
+        let start = self.offset;
+        let max_line_len = self.limits.max_line_len;
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Err(violation(self.offset, ChunkedViolation::Malformed));
+            }
+            let newline = available.iter().position(|&b| b == b'\n');
+            let used = newline.map_or(available.len(), |i| i + 1);
+            let content = &available[..newline.unwrap_or(used)];
+            // The CR ending the line is not counted against the limit, and may be the last byte buffered.
+            let line_len = self.line.len() + content.len();
+            let cr_len = usize::from(match content.last() {
+                Some(&b) => b == b'\r',
+                None => self.line.last() == Some(&b'\r'),
+            });
+            if line_len.saturating_sub(cr_len) > max_line_len {
+                return Err(violation(
+                    start,
+                    ChunkedViolation::LineTooLong { max_line_len },
+                ));
+            }
+            self.line.extend_from_slice(content);
+            self.consume(used);
+            if newline.is_some() {
+                break;
+            }
+        }
+
+        let malformed = || violation(start, ChunkedViolation::Malformed);
+        let line = self.line.strip_suffix(b"\r").ok_or_else(malformed)?;
+        let (digits, rest) =
+            line.split_at(line.iter().take_while(|b| b.is_ascii_hexdigit()).count());
+        // Chunk extensions follow a `;`, possibly after whitespace.
+        let extensions =
+            rest.is_empty() || rest.iter().find(|&&b| b != b' ' && b != b'\t') == Some(&b';');
+        let size = std::str::from_utf8(digits)
+            .ok()
+            .filter(|digits| !digits.is_empty() && extensions)
+            .and_then(|digits| u64::from_str_radix(digits, 16).ok())
+            .ok_or_else(malformed)?;
+
+        if size == 0 {
+            self.state = State::Trailer { len: 0, cr: false };
+            return Ok(());
+        }
+        if let Some(max_chunks) = self.limits.max_chunks {
+            if self.chunks >= max_chunks {
+                return Err(violation(
+                    start,
+                    ChunkedViolation::TooManyChunks { max_chunks },
+                ));
+            }
+        }
+        if let Some(max_decoded_len) = self.limits.max_decoded_len {
+            if self.decoded.saturating_add(size) > max_decoded_len {
+                return Err(violation(
+                    start,
+                    ChunkedViolation::BodyTooLong { max_decoded_len },
+                ));
+            }
+        }
+        self.chunks += 1;
+        self.state = State::Data { remaining: size };
+
+        Ok(())
+    }
+
+    // Reads the CRLF ending the data of a chunk, after the CR if `cr`.
+    fn read_data_end(&mut self, cr: bool) -> io::Result<()> {
+        match (cr, self.reader.fill_buf()?.first()) {
+            (false, Some(b'\r')) => {
+                self.consume(1);
+                self.state = State::DataEnd { cr: true };
+            }
+            (true, Some(b'\n')) => {
+                self.consume(1);
+                self.state = State::Size;
+            }
+            _ => return Err(violation(self.offset, ChunkedViolation::Malformed)),
+        }
+
+        Ok(())
+    }
+
+    // Reads the trailer section up to the blank line ending it, discarding its fields.
+    fn read_trailer(&mut self, mut len: u64, mut cr: bool) -> io::Result<()> {
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Err(violation(self.offset, ChunkedViolation::Malformed));
+            }
+            let newline = available.iter().position(|&b| b == b'\n');
+            let used = newline.map_or(available.len(), |i| i + 1);
+            let content = &available[..newline.unwrap_or(used)];
+            if let Some(&last) = content.last() {
+                cr = last == b'\r';
+            }
+            len += content.len() as u64;
+            if let Some(i) = newline.filter(|_| !cr) {
+                return Err(violation(
+                    self.offset + i as u64,
+                    ChunkedViolation::Malformed,
+                ));
+            }
+            self.trailer_len += used as u64;
+            if let Some(max_trailer_len) = self.limits.max_trailer_len {
+                if self.trailer_len > max_trailer_len {
+                    return Err(violation(
+                        self.offset,
+                        ChunkedViolation::TrailerTooLong { max_trailer_len },
+                    ));
+                }
+            }
+            self.consume(used);
+            if newline.is_some() {
+                if len == 1 {
+                    self.state = State::Done;
+                    return Ok(());
+                }
+                (len, cr) = (0, false);
+            }
+            self.state = State::Trailer { len, cr };
+        }
+    }
+}
+
+impl<R: BufRead> Read for ChunkedDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.state {
+                State::Size => self.read_size()?,
+                State::Data { remaining } => {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+                    let available = self.reader.fill_buf()?;
+                    if available.is_empty() {
+                        return Err(violation(self.offset, ChunkedViolation::Malformed));
+                    }
+                    let n = available
+                        .len()
+                        .min(buf.len())
+                        .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+                    buf[..n].copy_from_slice(&available[..n]);
+                    self.consume(n);
+                    self.decoded += n as u64;
+                    self.state = match remaining - n as u64 {
+                        0 => State::DataEnd { cr: false },
+                        remaining => State::Data { remaining },
+                    };
+
+                    return Ok(n);
+                }
+                State::DataEnd { cr } => self.read_data_end(cr)?,
+                State::Trailer { len, cr } => self.read_trailer(len, cr)?,
+                State::Done => return Ok(0),
+            }
+        }
+    }
+}
+
+fn violation(offset: u64, violation: ChunkedViolation) -> io::Error {
+    let kind = match violation {
+        ChunkedViolation::Malformed => ErrorKind::Deserialize,
+        _ => ErrorKind::ChunkedLimitExceeded,
+    };
+
+    LimitReaderError::new(kind, ChunkedLimitError { offset, violation }).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Encoding, LimitReader};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    // Chunks `body` into chunks of `size` bytes, ending with `trailer`.
+    fn chunked(body: &[u8], size: usize, trailer: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        for chunk in body.chunks(size) {
+            write!(out, "{:x}\r\n", chunk.len()).unwrap();
+            out.extend_from_slice(chunk);
+            out.extend_from_slice(b"\r\n");
+        }
+        write!(out, "0\r\n{trailer}\r\n").unwrap();
+
+        out
+    }
+
+    fn decode(body: &[u8], limits: &ChunkedLimits) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        ChunkedDecoder::new(body, limits).read_to_end(&mut out)?;
+
+        Ok(out)
+    }
+
+    fn violation(err: io::Error) -> (ErrorKind, ChunkedLimitError) {
+        let err = LimitReaderError::from_io(err, ErrorKind::ReadError);
+        let chunked_err = *err.get_ref().downcast_ref::<ChunkedLimitError>().unwrap();
+
+        (err.kind(), chunked_err)
+    }
+
+    #[test]
+    fn decodes_chunked_body() {
+        let body = b"4;name=value\r\nMike\r\nA\r\n was here.\r\n0\r\nExpires: never\r\n\r\nGET /";
+        let mut decoder = ChunkedDecoder::new(&body[..], &ChunkedLimits::new());
+        let mut out = Vec::new();
+        decoder.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"Mike was here.");
+        assert_eq!(decoder.chunk_count(), 2);
+        assert_eq!(decoder.into_inner(), b"GET /");
+
+        let body = chunked(b"Mike was here.", 3, "");
+        assert_eq!(
+            decode(&body, &ChunkedLimits::new()).unwrap(),
+            b"Mike was here."
+        );
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let body = chunked(b"Mike was here.", 4, "Expires: never\r\n");
+        let check =
+            |limits: &ChunkedLimits, body: &[u8]| violation(decode(body, limits).unwrap_err());
+
+        let (kind, err) = check(ChunkedLimits::new().max_chunks(3), &body);
+        assert_eq!(kind, ErrorKind::ChunkedLimitExceeded);
+        assert_eq!(
+            err.violation(),
+            ChunkedViolation::TooManyChunks { max_chunks: 3 }
+        );
+        assert_eq!(err.offset(), 27);
+
+        let (_, err) = check(
+            ChunkedLimits::new().max_decoded_len(1 << 20),
+            b"ffffffffffffffff\r\n",
+        );
+        assert_eq!(
+            err.violation(),
+            ChunkedViolation::BodyTooLong {
+                max_decoded_len: 1 << 20
+            }
+        );
+
+        let (_, err) = check(
+            ChunkedLimits::new().max_line_len(8),
+            b"1\r\nx\r\n1;padding=xxxxxxxxxxxxxxxx\r\n",
+        );
+        assert_eq!(
+            err.violation(),
+            ChunkedViolation::LineTooLong { max_line_len: 8 }
+        );
+        assert_eq!(err.offset(), 6);
+        decode(
+            b"1;abcdef\r\nx\r\n0\r\n\r\n",
+            ChunkedLimits::new().max_line_len(8),
+        )
+        .unwrap();
+
+        let (_, err) = check(ChunkedLimits::new().max_trailer_len(17), &body);
+        assert_eq!(
+            err.violation(),
+            ChunkedViolation::TrailerTooLong {
+                max_trailer_len: 17
+            }
+        );
+        decode(&body, ChunkedLimits::new().max_trailer_len(18)).unwrap();
+
+        let trailer = format!("X-Padding: {}\r\n", "x".repeat(8 * 1024));
+        let (_, err) = check(&ChunkedLimits::default(), &chunked(b"Mike", 4, &trailer));
+        assert_eq!(
+            err.violation(),
+            ChunkedViolation::TrailerTooLong {
+                max_trailer_len: 8 * 1024
+            }
+        );
+
+        for malformed in [&b"4\r\nMike0\r\n\r\n"[..], b"x\r\n", b"4\r\nMi", b"0\r\n"] {
+            let (kind, err) = check(&ChunkedLimits::new(), malformed);
+            assert_eq!(kind, ErrorKind::Deserialize);
+            assert_eq!(err.violation(), ChunkedViolation::Malformed);
+        }
+    }
+
+    #[test]
+    fn requires_strict_framing() {
+        for (malformed, offset) in [
+            (&b" 4\r\nMike\r\n0\r\n\r\n"[..], 0),
+            (b"4 \r\nMike\r\n0\r\n\r\n", 0),
+            (b"4 x;a\r\nMike\r\n0\r\n\r\n", 0),
+            (b"4\nMike\r\n0\r\n\r\n", 0),
+            (b"4\r\nMike\n0\r\n\r\n", 7),
+            (b"0\r\n\n", 3),
+            (b"0\r\nExpires: never\n\r\n", 17),
+            (b"0\r\n\r\r\n", 6),
+        ] {
+            let err = decode(malformed, &ChunkedLimits::new()).unwrap_err();
+            let (_, err) = violation(err);
+            assert_eq!(err.violation(), ChunkedViolation::Malformed);
+            assert_eq!(
+                err.offset(),
+                offset,
+                "{:?}",
+                String::from_utf8_lossy(malformed)
+            );
+        }
+
+        let body = b"4 \t;name=value\r\nMike\r\n0\r\nExpires: never\r\n\r\n";
+        assert_eq!(decode(body, &ChunkedLimits::new()).unwrap(), b"Mike");
+    }
+
+    #[test]
+    fn composes_with_content_decoding() {
+        let text = "Mike was here. Briefly.".repeat(20);
+        let mut e = GzEncoder::new(Vec::new(), Compression::default());
+        e.write_all(text.as_bytes()).unwrap();
+        let body = chunked(&e.finish().unwrap(), 7, "");
+
+        let mut out = Vec::new();
+        let output = LimitReader::new()
+            .decode_chunked(&ChunkedLimits::new())
+            .enable_decode_auto()
+            .copy(body.as_slice(), &mut out)
+            .unwrap();
+        assert_eq!(out, text.as_bytes());
+        assert_eq!(output.encoding(), Encoding::Gzip);
+        assert_eq!(output.source_size(), body.len() as u64);
+
+        let err = LimitReader::new()
+            .decode_chunked(ChunkedLimits::new().max_chunks(2))
+            .enable_decode_auto()
+            .copy(body.as_slice(), &mut out)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ChunkedLimitExceeded);
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn composes_with_brotli() {
+        let text = "Mike was here. Briefly.".repeat(20);
+        let mut e = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+        e.write_all(text.as_bytes()).unwrap();
+        let body = chunked(&e.into_inner(), 5, "");
+
+        let mut out = Vec::new();
+        let output = LimitReader::new()
+            .limit(1024)
+            .decode_chunked(&ChunkedLimits::new())
+            .enable_decode_brotli()
+            .copy(body.as_slice(), &mut out)
+            .unwrap();
+        assert_eq!(out, text.as_bytes());
+        assert_eq!(output.encoding(), Encoding::Brotli);
+    }
+}
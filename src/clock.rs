@@ -0,0 +1,65 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A source of time for deadlines, idle timeouts and rate limits.  Inject a [`ManualClock`] to test time-dependent limits without waiting.
+pub trait Clock: Send + Sync {
+    /// The current instant.
+    fn now(&self) -> Instant;
+
+    /// Block the current thread for `duration`, as a rate limit requires.
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// The system's monotonic clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when advanced, e.g. by a fake reader in tests.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<Instant>,
+}
+
+impl ManualClock {
+    /// Create a clock stopped at the current instant.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            now: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Move the clock forward by `duration`.
+    ///
+    /// # Panics
+    ///
+    /// If another thread panicked while advancing the clock.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().expect("clock lock poisoned") += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().expect("clock lock poisoned")
+    }
+
+    /// Advance the clock by `duration` rather than blocking.
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::error::{ErrorKind, LimitReaderError};
+use crate::{LimitReader, LimitReaderResult as Result};
+use serde::de::DeserializeOwned;
+use std::io::{self, Read};
+
+impl LimitReader {
+    /// Deserialize a JSON value from `source`, decoded as configured, streaming it through a [`crate::LimitReaderFallible`] into the deserializer without an intermediate copy.  Digests, tees and observers do not apply, while [`LimitReader::max_ratio`] and [`LimitReader::min_size`] are enforced as the source is read.
+    ///
+    /// # Errors
+    ///
+    /// Once the source exceeds the limit, this returns a [`LimitReaderError`] of the kind [`ErrorKind::LimitExceeded`], rather than a parse error at the point of truncation.  Malformed JSON returns [`ErrorKind::Deserialize`].
+    ///
+    #[cfg(feature = "json")]
+    pub fn deserialize_json<T: DeserializeOwned>(&self, source: impl Read) -> Result<T> {
+        let reader = self.strict_reader(source)?;
+
+        serde_json::from_reader(reader).map_err(|err| from_io(err.into()))
+    }
+
+    /// Like [`LimitReader::deserialize_json`], rejecting documents that violate `limits` before the offending part reaches the deserializer; see [`crate::JsonReader`].
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors of [`LimitReader::deserialize_json`], this returns a [`LimitReaderError`] of the kind [`ErrorKind::JsonLimitExceeded`] if the document violates `limits`.
+    ///
+    #[cfg(feature = "json")]
+    pub fn deserialize_json_with<T: DeserializeOwned>(
+        &self,
+        source: impl Read,
+        limits: &crate::JsonLimits,
+    ) -> Result<T> {
+        let reader = crate::JsonReader::new(self.strict_reader(source)?, limits);
+
+        serde_json::from_reader(reader).map_err(|err| from_io(err.into()))
+    }
+
+    /// Like [`LimitReader::deserialize_json`], for the `bincode` format as written by `bincode::serialize`.  Lengths declared by the source are checked against the limit before allocating.
+    ///
+    /// # Errors
+    ///
+    /// Once the source exceeds the limit, or declares a length exceeding it, this returns a [`LimitReaderError`] of the kind [`ErrorKind::LimitExceeded`].  Malformed input returns [`ErrorKind::Deserialize`].
+    ///
+    #[cfg(feature = "bincode")]
+    pub fn deserialize_bincode<T: DeserializeOwned>(&self, source: impl Read) -> Result<T> {
+        use bincode::Options;
+
+        let reader = self.strict_reader(source)?;
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(self.expected_size)
+            .deserialize_from(reader)
+            .map_err(|err| match *err {
+                bincode::ErrorKind::Io(err) => from_io(err),
+                bincode::ErrorKind::SizeLimit => {
+                    LimitReaderError::new(ErrorKind::LimitExceeded, "declared length exceeds limit")
+                }
+                err => LimitReaderError::new(ErrorKind::Deserialize, err),
+            })
+    }
+
+    /// Like [`LimitReader::deserialize_json`], for CBOR.
+    ///
+    /// # Errors
+    ///
+    /// Once the source exceeds the limit, this returns a [`LimitReaderError`] of the kind [`ErrorKind::LimitExceeded`].  Malformed input returns [`ErrorKind::Deserialize`].
+    ///
+    #[cfg(feature = "cbor")]
+    pub fn deserialize_cbor<T: DeserializeOwned>(&self, source: impl Read) -> Result<T> {
+        let reader = self.strict_reader(source)?;
+
+        ciborium::de::from_reader(reader).map_err(|err| match err {
+            ciborium::de::Error::Io(err) => from_io(err),
+            err => LimitReaderError::new(ErrorKind::Deserialize, err.to_string()),
+        })
+    }
+}
+
+// Unwraps an error raised by the limit adapter, such as `LimitExceeded`, from within the deserializer.
+fn from_io(err: io::Error) -> LimitReaderError {
+    LimitReaderError::from_io(err, ErrorKind::Deserialize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Upload {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    fn upload() -> Upload {
+        Upload {
+            name: "Mike was here".to_string(),
+            tags: vec!["briefly".to_string(); 4],
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_past_limit_is_limit_exceeded() {
+        let json = serde_json::to_vec(&upload()).unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader.limit(json.len() as u64);
+        let decoded: Upload = limit_reader.deserialize_json(json.as_slice()).unwrap();
+        assert_eq!(decoded, upload());
+
+        limit_reader.limit(json.len() as u64 - 10);
+        let err = limit_reader
+            .deserialize_json::<Upload>(json.as_slice())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+
+        let err = limit_reader
+            .deserialize_json::<Upload>(&b"{\"name\": 1}"[..])
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Deserialize);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_structure_is_checked_before_deserializing() {
+        let json = serde_json::to_vec(&upload()).unwrap();
+        let mut limits = crate::JsonLimits::new();
+        limits.max_len(3);
+
+        let limit_reader = LimitReader::new();
+        let err = limit_reader
+            .deserialize_json_with::<Upload>(json.as_slice(), &limits)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::JsonLimitExceeded);
+        let err = err
+            .get_ref()
+            .downcast_ref::<crate::JsonLimitError>()
+            .unwrap();
+        assert_eq!(err.pointer(), "/tags");
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_checks_declared_lengths() {
+        let bytes = bincode::serialize(&upload()).unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        let decoded: Upload = limit_reader.deserialize_bincode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, upload());
+
+        // A name declaring a length of 4 GiB.
+        let hostile = [0, 0, 0, 0, 1, 0, 0, 0];
+        limit_reader.limit(1024 * 1024);
+        let err = limit_reader
+            .deserialize_bincode::<Upload>(&hostile[..])
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_past_limit_is_limit_exceeded() {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&upload(), &mut bytes).unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        let decoded: Upload = limit_reader.deserialize_cbor(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, upload());
+
+        limit_reader.limit(16);
+        let err = limit_reader
+            .deserialize_cbor::<Upload>(bytes.as_slice())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+    }
+}
@@ -0,0 +1,503 @@
+use crate::error::{ErrorKind, LimitReaderError};
+use crate::{LimitReaderResult as Result, Stage};
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Read};
+
+// `sha1` and `sha2` share the `Digest` trait.
+#[cfg(all(feature = "sha1", not(feature = "sha2")))]
+use sha1::Digest as _;
+#[cfg(feature = "sha2")]
+use sha2::Digest as _;
+
+/// A digest algorithm, enabled by the feature of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum DigestAlgorithm {
+    /// SHA-1, enabled by the `sha1` feature.
+    #[cfg(feature = "sha1")]
+    Sha1,
+    /// SHA-256, enabled by the `sha2` feature.
+    #[cfg(feature = "sha2")]
+    Sha256,
+    /// BLAKE3, enabled by the `blake3` feature.
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+impl DigestAlgorithm {
+    /// The name of the algorithm.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            #[cfg(feature = "sha1")]
+            DigestAlgorithm::Sha1 => "sha1",
+            #[cfg(feature = "sha2")]
+            DigestAlgorithm::Sha256 => "sha256",
+            #[cfg(feature = "blake3")]
+            DigestAlgorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+impl Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A digest computed while reading; see [`crate::LimitReader::digest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Digest {
+    algorithm: DigestAlgorithm,
+    stage: Stage,
+    value: Vec<u8>,
+    partial: bool,
+}
+
+impl Digest {
+    /// The algorithm of the digest.
+    #[must_use]
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    /// The bytes the digest was computed over.
+    #[must_use]
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Whether the digest covers only the start of its stage, as a raw digest does once the rest of the source exceeds the bound described by [`crate::LimitReader::digest`].
+    #[must_use]
+    pub fn partial(&self) -> bool {
+        self.partial
+    }
+
+    /// The digest value.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// The digest value as lowercase hex.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.value)
+    }
+}
+
+impl Display for Digest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.to_hex())
+    }
+}
+
+/// Structured error for [`ErrorKind::DigestMismatch`], raised when a digest set by [`crate::LimitReader::expect_digest`] does not match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestMismatchError {
+    expected: String,
+    actual: Digest,
+}
+
+impl DigestMismatchError {
+    /// The expected digest, as lowercase hex.
+    #[must_use]
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// The digest actually computed.
+    #[must_use]
+    pub fn actual(&self) -> &Digest {
+        &self.actual
+    }
+}
+
+impl Display for DigestMismatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} digest of {}{} bytes is {}, expected {}",
+            self.actual.algorithm,
+            if self.actual.partial { "partial " } else { "" },
+            self.actual.stage,
+            self.actual.to_hex(),
+            self.expected
+        )
+    }
+}
+
+impl std::error::Error for DigestMismatchError {}
+
+/// Digests to compute while reading, and the values some of them are expected to have.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct DigestConfig {
+    digests: Vec<(DigestAlgorithm, Stage)>,
+    expected: Vec<(DigestAlgorithm, Stage, String)>,
+}
+
+impl DigestConfig {
+    pub(crate) fn add(&mut self, algorithm: DigestAlgorithm, stage: Stage) {
+        if !self.digests.contains(&(algorithm, stage)) {
+            self.digests.push((algorithm, stage));
+        }
+    }
+
+    pub(crate) fn expect(&mut self, algorithm: DigestAlgorithm, stage: Stage, hex: &str) {
+        self.add(algorithm, stage);
+        self.expected
+            .push((algorithm, stage, hex.to_ascii_lowercase()));
+    }
+
+    /// Wrap `inner` to compute all digests configured for `stage`.
+    pub(crate) fn reader<R: Read>(&self, inner: R, stage: Stage) -> DigestReader<R> {
+        let hashers = self
+            .digests
+            .iter()
+            .filter(|(_, s)| *s == stage)
+            .map(|(algorithm, _)| Hasher::new(*algorithm))
+            .collect();
+
+        DigestReader {
+            inner,
+            stage,
+            hashers,
+        }
+    }
+
+    /// Check `digests` against the expected values.
+    // Without any digest feature there are no digests to check.
+    #[cfg_attr(
+        not(any(feature = "sha1", feature = "sha2", feature = "blake3")),
+        allow(unreachable_code)
+    )]
+    pub(crate) fn verify(&self, digests: &[Digest]) -> Result<()> {
+        for (algorithm, stage, expected) in &self.expected {
+            let actual = digests
+                .iter()
+                .find(|d| d.algorithm == *algorithm && d.stage == *stage);
+            if let Some(actual) = actual {
+                if actual.to_hex() != *expected {
+                    return Err(LimitReaderError::new(
+                        ErrorKind::DigestMismatch,
+                        DigestMismatchError {
+                            expected: expected.clone(),
+                            actual: actual.clone(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An incremental hasher for one [`DigestAlgorithm`].
+#[derive(Clone)]
+enum Hasher {
+    #[cfg(feature = "sha1")]
+    Sha1(sha1::Sha1),
+    #[cfg(feature = "sha2")]
+    Sha256(sha2::Sha256),
+    #[cfg(feature = "blake3")]
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: DigestAlgorithm) -> Self {
+        match algorithm {
+            #[cfg(feature = "sha1")]
+            DigestAlgorithm::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            #[cfg(feature = "sha2")]
+            DigestAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            #[cfg(feature = "blake3")]
+            DigestAlgorithm::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            #[cfg(feature = "sha1")]
+            Hasher::Sha1(hasher) => hasher.update(data),
+            #[cfg(feature = "sha2")]
+            Hasher::Sha256(hasher) => hasher.update(data),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            #[cfg(not(any(feature = "sha1", feature = "sha2", feature = "blake3")))]
+            _ => {
+                let _ = data;
+            }
+        }
+    }
+
+    fn finalize(self) -> (DigestAlgorithm, Vec<u8>) {
+        match self {
+            #[cfg(feature = "sha1")]
+            Hasher::Sha1(hasher) => (DigestAlgorithm::Sha1, hasher.finalize().to_vec()),
+            #[cfg(feature = "sha2")]
+            Hasher::Sha256(hasher) => (DigestAlgorithm::Sha256, hasher.finalize().to_vec()),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(hasher) => (
+                DigestAlgorithm::Blake3,
+                hasher.finalize().as_bytes().to_vec(),
+            ),
+        }
+    }
+}
+
+/// Computes digests over all bytes read through it.
+pub(crate) struct DigestReader<R> {
+    inner: R,
+    stage: Stage,
+    hashers: Vec<Hasher>,
+}
+
+impl<R: Read> DigestReader<R> {
+    /// Whether any digest is computed.
+    pub(crate) fn has_digests(&self) -> bool {
+        !self.hashers.is_empty()
+    }
+
+    /// Finish computing the digests, which cover only the start of the stream if `partial`.
+    pub(crate) fn finish(self, partial: bool) -> Vec<Digest> {
+        let stage = self.stage;
+
+        self.hashers
+            .into_iter()
+            .map(|hasher| {
+                let (algorithm, value) = hasher.finalize();
+                Digest {
+                    algorithm,
+                    stage,
+                    value,
+                    partial,
+                }
+            })
+            .collect()
+    }
+}
+
+impl<R: Read> Read for DigestReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        for hasher in &mut self.hashers {
+            hasher.update(&buf[..n]);
+        }
+
+        Ok(n)
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    bytes.iter().fold(String::new(), |mut hex, b| {
+        let _ = write!(hex, "{b:02x}");
+        hex
+    })
+}
+
+#[cfg(all(test, feature = "sha2"))]
+mod tests {
+    use super::*;
+    use crate::LimitReader;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    // SHA-256 of "Mike was here. Briefly."
+    const TEXT_SHA256: &str = "7e5ad34c0758bdf00af34bd62207dbd15ef3b3141f5d9b86ea168a175c41b8e9";
+
+    #[test]
+    fn computes_raw_and_decoded_digests() {
+        let dir = tempdir().unwrap();
+
+        let text = "Mike was here. Briefly.";
+        let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
+        e.write_all(text.as_bytes()).unwrap();
+        let compressed = e.finish().unwrap();
+
+        let file_path = dir.path().join("test_output.txt.z");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(&compressed).unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader
+            .enable_decode_zlib()
+            .digest(DigestAlgorithm::Sha256, Stage::Raw)
+            .expect_digest(DigestAlgorithm::Sha256, Stage::Decoded, TEXT_SHA256);
+
+        let output = limit_reader.read_limited(file_path).unwrap();
+        let digests = output.digests();
+        assert_eq!(digests.len(), 2);
+        assert_eq!(digests[0].stage(), Stage::Raw);
+        assert_ne!(digests[0].to_hex(), TEXT_SHA256);
+        assert_eq!(digests[1].stage(), Stage::Decoded);
+        assert_eq!(digests[1].to_string(), format!("sha256:{TEXT_SHA256}"));
+        assert_eq!(limit_reader.digests(), digests);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn raw_digest_covers_whole_source() {
+        let dir = tempdir().unwrap();
+
+        let (text, compressed) = incompressible(16 * 1024);
+        let raw_sha256 = to_hex(&sha2::Sha256::digest(&compressed));
+
+        let file_path = dir.path().join("test_output.txt.z");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(&compressed).unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader
+            .limit(100)
+            .enable_decode_zlib()
+            .expect_digest(DigestAlgorithm::Sha256, Stage::Raw, &raw_sha256)
+            .digest(DigestAlgorithm::Sha256, Stage::Decoded);
+        let output = limit_reader.read_limited(file_path).unwrap();
+        assert!(output.truncated());
+        assert_eq!(output.compressed_bytes(), compressed.len() as u64);
+        let decoded_sha256 = to_hex(&sha2::Sha256::digest(&text[..100]));
+        assert_eq!(output.digests()[1].to_hex(), decoded_sha256);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+
+    // Barely compressible text, compressed into more than the read-ahead of the decoder.
+    fn incompressible(len: usize) -> (Vec<u8>, Vec<u8>) {
+        let mut seed = 1_u32;
+        let text: Vec<u8> = (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect();
+        let mut e = ZlibEncoder::new(Vec::new(), Compression::fast());
+        e.write_all(&text).unwrap();
+        let compressed = e.finish().unwrap();
+        assert!(compressed.len() > 8 * 1024);
+
+        (text, compressed)
+    }
+
+    #[test]
+    fn raw_digest_of_stream_is_bounded_by_limit() {
+        let (_, compressed) = incompressible(16 * 1024);
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader
+            .limit(100)
+            .enable_decode_zlib()
+            .digest(DigestAlgorithm::Sha256, Stage::Raw);
+        let output = limit_reader
+            .copy_limited(compressed.as_slice(), &mut Vec::new())
+            .unwrap();
+        assert!(output.digests()[0].partial());
+        assert!(output.compressed_bytes() < compressed.len() as u64);
+
+        limit_reader.limit(1 << 20);
+        let output = limit_reader
+            .copy_limited(compressed.as_slice(), &mut Vec::new())
+            .unwrap();
+        assert!(!output.digests()[0].partial());
+        assert_eq!(
+            output.digests()[0].to_hex(),
+            to_hex(&sha2::Sha256::digest(&compressed))
+        );
+    }
+
+    // Cancels its token once more than `after` bytes were written to it.
+    struct CancelAfter {
+        after: usize,
+        token: crate::CancellationToken,
+    }
+
+    impl Write for CancelAfter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.after = self.after.saturating_sub(buf.len());
+            if self.after == 0 {
+                self.token.cancel();
+            }
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn raw_digest_drain_is_cancelled() {
+        let dir = tempdir().unwrap();
+
+        let (_, compressed) = incompressible(64 * 1024);
+        let file_path = dir.path().join("test_output.txt.z");
+        File::create(&file_path)
+            .unwrap()
+            .write_all(&compressed)
+            .unwrap();
+
+        // The read itself consumes the read-ahead of the decoder, leaving the rest to the drain.
+        let token = crate::CancellationToken::new();
+        let tee = CancelAfter {
+            after: 12 * 1024,
+            token: token.clone(),
+        };
+        let mut limit_reader = LimitReader::new();
+        limit_reader
+            .limit(100)
+            .enable_decode_zlib()
+            .cancel_token(token)
+            .tee(tee, Stage::Raw, u64::MAX)
+            .digest(DigestAlgorithm::Sha256, Stage::Raw);
+        let err = limit_reader.read_limited(file_path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        let output = err
+            .get_ref()
+            .downcast_ref::<crate::CancelledError>()
+            .unwrap()
+            .output();
+        assert_eq!(output.bytes_read(), 100);
+        assert!(output.compressed_bytes() < compressed.len() as u64);
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn rejects_mismatched_digest() {
+        let dir = tempdir().unwrap();
+
+        let file_path = dir.path().join("test_output.txt");
+        let mut file = File::create(&file_path).unwrap();
+        write!(file, "Mike was here. Briefly!").unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader.expect_digest(
+            DigestAlgorithm::Sha256,
+            Stage::Raw,
+            &TEXT_SHA256.to_uppercase(),
+        );
+
+        let err = limit_reader.read(file_path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DigestMismatch);
+        let mismatch = err.get_ref().downcast_ref::<DigestMismatchError>().unwrap();
+        assert_eq!(mismatch.expected(), TEXT_SHA256);
+        assert_eq!(mismatch.actual(), &limit_reader.digests()[0]);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+}
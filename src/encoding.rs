@@ -0,0 +1,149 @@
+use flate2::bufread::{MultiGzDecoder, ZlibDecoder};
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, Read};
+
+/// The compression of a source, as decoded by [`crate::LimitReader`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum Encoding {
+    /// Not compressed.
+    #[default]
+    Identity,
+    /// Zlib (RFC 1950).
+    Zlib,
+    /// Gzip (RFC 1952), possibly of several members.
+    Gzip,
+    /// Brotli (RFC 7932).
+    #[cfg(feature = "brotli")]
+    Brotli,
+}
+
+impl Encoding {
+    /// Detect the encoding from the first bytes of a source, falling back to [`Encoding::Identity`].
+    #[must_use]
+    pub fn detect(prefix: &[u8]) -> Self {
+        match prefix {
+            [0x1f, 0x8b, ..] => Self::Gzip,
+            // The compression method is deflate, and the header check bits make the header a multiple of 31.
+            [cmf, flg, ..]
+                if cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0 =>
+            {
+                Self::Zlib
+            }
+            _ => Self::Identity,
+        }
+    }
+
+    /// The lowercase name of the encoding, e.g. `gzip`.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Zlib => "zlib",
+            Self::Gzip => "gzip",
+            #[cfg(feature = "brotli")]
+            Self::Brotli => "brotli",
+        }
+    }
+
+    /// Wrap `r` in the decoder for this encoding.
+    pub(crate) fn decoder<'a>(self, r: impl BufRead + 'a) -> Box<dyn Read + 'a> {
+        match self {
+            Self::Identity => Box::new(r),
+            Self::Zlib => Box::new(ZlibDecoder::new(r)),
+            Self::Gzip => Box::new(MultiGzDecoder::new(r)),
+            #[cfg(feature = "brotli")]
+            Self::Brotli => Box::new(brotli::Decompressor::new(r, 4096)),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LimitReader;
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::{self, Write};
+
+    #[test]
+    fn detects_magic_bytes() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::best());
+        zlib.write_all(b"x").unwrap();
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(b"x").unwrap();
+
+        assert_eq!(Encoding::detect(&zlib.finish().unwrap()), Encoding::Zlib);
+        assert_eq!(Encoding::detect(&gzip.finish().unwrap()), Encoding::Gzip);
+        assert_eq!(Encoding::detect(b"Mike was here."), Encoding::Identity);
+        assert_eq!(Encoding::detect(b"x"), Encoding::Identity);
+    }
+
+    #[test]
+    fn auto_decodes_stream() {
+        let text = "Mike was here. Briefly.";
+        let mut e = GzEncoder::new(Vec::new(), Compression::default());
+        e.write_all(text.as_bytes()).unwrap();
+        let compressed = e.finish().unwrap();
+
+        let mut out = Vec::new();
+        let mut limit_reader = LimitReader::new();
+        let output = limit_reader
+            .enable_decode_auto()
+            .copy(compressed.as_slice(), &mut out)
+            .unwrap();
+        assert_eq!(out, text.as_bytes());
+        assert_eq!(output.encoding(), Encoding::Gzip);
+        assert_eq!(output.source_size(), compressed.len() as u64);
+
+        out.clear();
+        let output = limit_reader.copy(text.as_bytes(), &mut out).unwrap();
+        assert_eq!(out, text.as_bytes());
+        assert_eq!(output.encoding(), Encoding::Identity);
+    }
+
+    // Returns a single byte per read, as a slow pipe may.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn detects_encoding_across_short_reads() {
+        let text = "Mike was here. Briefly.";
+        let mut e = GzEncoder::new(Vec::new(), Compression::default());
+        e.write_all(text.as_bytes()).unwrap();
+        let compressed = e.finish().unwrap();
+
+        let mut out = Vec::new();
+        let output = LimitReader::new()
+            .enable_decode_auto()
+            .copy(Trickle(&compressed), &mut out)
+            .unwrap();
+        assert_eq!(out, text.as_bytes());
+        assert_eq!(output.encoding(), Encoding::Gzip);
+
+        out.clear();
+        let output = LimitReader::new()
+            .enable_decode_auto()
+            .copy(Trickle(b"x"), &mut out)
+            .unwrap();
+        assert_eq!(out, b"x");
+        assert_eq!(output.encoding(), Encoding::Identity);
+    }
+}
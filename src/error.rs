@@ -16,29 +16,89 @@ pub struct Error {
     error: BoxError,
 }
 
-#[derive(Debug)]
+/// The kind of a [`LimitReaderError`], allowing callers to distinguish failures without matching on messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 #[allow(clippy::module_name_repetitions)]
 #[allow(clippy::enum_variant_names)]
 pub enum ErrorKind {
+    /// Reading from the underlying reader failed.
     ReadError,
+    /// An I/O operation, such as opening the source, failed.
     IoError,
+    /// Building a [`crate::LimitReaderOutput`] failed.
     LimitReaderOutputBuilderError,
+    /// Bytes read were not valid UTF-8.
     Utf8Error,
+    /// An integer conversion failed.
     TryFromIntError,
+    /// A line exceeded `max_line_len`, or the input exceeded `max_lines`; see [`crate::LineLimitError`].
+    LineLimitExceeded,
+    /// The source held more bytes than the limit allows.
+    LimitExceeded,
+    /// The source ended before the minimum or exact size was read; see [`UnexpectedEofError`].
+    UnexpectedEof,
+    /// A git object or its header is malformed.
+    InvalidObject,
+    /// The SHA-1 of a git object does not match its expected id.
+    ObjectIdMismatch,
+    /// A digest set by [`crate::LimitReader::expect_digest`] did not match; see [`crate::DigestMismatchError`].
+    DigestMismatch,
+    /// A read exceeded its deadline or idle timeout; see [`crate::TimeoutError`].
+    Timeout,
+    /// A read was cancelled by its [`crate::CancellationToken`]; see [`crate::CancelledError`].
+    Cancelled,
+    /// An [`crate::Observer`] aborted the read.
+    Vetoed,
+    /// An [`crate::Inspector`] rejected the content of the stream; see [`crate::RejectedError`].
+    Rejected,
+    /// A [`crate::SharedBudget`] was exhausted; see [`crate::BudgetExceededError`].
+    BudgetExceeded,
+    /// Decoding expanded the source beyond [`crate::LimitReader::max_ratio`]; see [`RatioExceededError`].
+    RatioExceeded,
+    /// A source read by one of the `deserialize_*` methods of [`crate::LimitReader`], by `load_yaml`, by an `XmlReader`, by a [`crate::FrameReader`] or by a [`crate::ChunkedDecoder`] is malformed.
+    Deserialize,
+    /// A JSON document violated its [`crate::JsonLimits`]; see [`crate::JsonLimitError`].
+    JsonLimitExceeded,
+    /// A YAML stream expanded beyond its `YamlLimits`, with the `yaml` feature; see `YamlLimitError`.
+    YamlLimitExceeded,
+    /// An XML document violated its `XmlLimits`, with the `xml` feature; see `XmlLimitError`.
+    XmlLimitExceeded,
+    /// A length-delimited frame violated its [`crate::FrameLimits`]; see [`crate::FrameLimitError`].
+    FrameLimitExceeded,
+    /// A chunked body violated its [`crate::ChunkedLimits`]; see [`crate::ChunkedLimitError`].
+    ChunkedLimitExceeded,
 }
 
 impl ErrorKind {
-    pub(crate) fn as_str(&self) -> &'static str {
+    pub(crate) fn as_str(self) -> &'static str {
         #[allow(clippy::enum_glob_use)]
         use ErrorKind::*;
         // tidy-alphabetical-start
-        match *self {
+        match self {
             ReadError => "read error",
             IoError => "io error",
             Utf8Error => "invalid utf-8",
             LimitReaderOutputBuilderError => "builder error",
             TryFromIntError => "conversion error",
+            LineLimitExceeded => "line limit exceeded",
+            LimitExceeded => "limit exceeded",
+            UnexpectedEof => "unexpected end of file",
+            InvalidObject => "invalid object",
+            ObjectIdMismatch => "object id mismatch",
+            DigestMismatch => "digest mismatch",
+            Timeout => "timed out",
+            Cancelled => "cancelled",
+            Vetoed => "vetoed by observer",
+            Rejected => "rejected",
+            BudgetExceeded => "budget exceeded",
+            RatioExceeded => "compression ratio exceeded",
+            Deserialize => "deserialization error",
+            JsonLimitExceeded => "json limit exceeded",
+            YamlLimitExceeded => "yaml limit exceeded",
+            XmlLimitExceeded => "xml limit exceeded",
+            FrameLimitExceeded => "frame limit exceeded",
+            ChunkedLimitExceeded => "chunked limit exceeded",
         }
     }
 }
@@ -68,6 +128,37 @@ impl Error {
     fn _new(kind: ErrorKind, error: BoxError) -> Error {
         Error { kind, error }
     }
+
+    /// Convert an [`std::io::Error`] returned by a reader into an `Error`.  An `Error` raised from within a reader, such as a limit adapter, is unwrapped with its original kind; any other error is wrapped with `kind`.
+    pub(crate) fn from_io(error: std::io::Error, kind: ErrorKind) -> Error {
+        match error.get_ref() {
+            Some(inner) if inner.is::<TooManyBytes>() => {
+                return Self::new(ErrorKind::LimitExceeded, TooManyBytes);
+            }
+            Some(inner) if inner.is::<Error>() => {}
+            _ => return Self::_new(kind, error.into()),
+        }
+
+        match error
+            .into_inner()
+            .map(<dyn StdError + Send + Sync>::downcast::<Error>)
+        {
+            Some(Ok(inner)) => *inner,
+            _ => unreachable!("inner error was checked to be an `Error`"),
+        }
+    }
+
+    /// Returns the corresponding [`ErrorKind`] for this error.
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns a reference to the inner error, which can be downcast to a structured error such as [`crate::LineLimitError`].
+    #[must_use]
+    pub fn get_ref(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        &*self.error
+    }
 }
 
 impl fmt::Debug for Error {
@@ -85,7 +176,19 @@ impl fmt::Display for Error {
     }
 }
 
-error_from!(std::io::Error, ErrorKind::IoError);
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::from_io(error, ErrorKind::IoError)
+    }
+}
+
+// Allows errors to be raised from within `Read` implementations, to be recovered by `Error::from_io`.
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> Self {
+        std::io::Error::other(error)
+    }
+}
+
 error_from!(FromUtf8Error, ErrorKind::Utf8Error);
 error_from!(
     LimitReaderOutputBuilderError,
@@ -111,6 +214,91 @@ pub mod macros {
     }
 }
 
+/// Structured error for [`ErrorKind::UnexpectedEof`], raised when the source ends before the size required by [`crate::LimitReader::exact_size`] or [`crate::LimitReader::min_size`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnexpectedEofError {
+    pub(crate) expected: u64,
+    pub(crate) actual: u64,
+}
+
+impl UnexpectedEofError {
+    /// The minimum number of bytes expected.
+    #[must_use]
+    pub fn expected(&self) -> u64 {
+        self.expected
+    }
+
+    /// The number of bytes actually read.
+    #[must_use]
+    pub fn actual(&self) -> u64 {
+        self.actual
+    }
+}
+
+impl fmt::Display for UnexpectedEofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unexpected end of file: expected {} bytes, read {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl StdError for UnexpectedEofError {}
+
+/// Structured error for [`ErrorKind::RatioExceeded`], raised when the decoded bytes outgrow the compressed bytes consumed by more than [`crate::LimitReader::max_ratio`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatioExceededError {
+    pub(crate) max_ratio: f64,
+    pub(crate) compressed_bytes: u64,
+    pub(crate) decoded_bytes: u64,
+}
+
+impl RatioExceededError {
+    /// The maximum ratio of decoded to compressed bytes allowed.
+    #[must_use]
+    pub fn max_ratio(&self) -> f64 {
+        self.max_ratio
+    }
+
+    /// Compressed bytes consumed when the ratio was exceeded.
+    #[must_use]
+    pub fn compressed_bytes(&self) -> u64 {
+        self.compressed_bytes
+    }
+
+    /// Decoded bytes produced when the ratio was exceeded.
+    #[must_use]
+    pub fn decoded_bytes(&self) -> u64 {
+        self.decoded_bytes
+    }
+}
+
+impl fmt::Display for RatioExceededError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "compression ratio exceeded: {} bytes decoded from {}, more than {}x",
+            self.decoded_bytes, self.compressed_bytes, self.max_ratio
+        )
+    }
+}
+
+impl StdError for RatioExceededError {}
+
+// Raised by `LimitReaderFallible` once the source exceeds the limit.  It is carried by `std::io::Error` as is, so the adapter's errors read "too many bytes", and recovered as `ErrorKind::LimitExceeded` by `Error::from_io`.
+#[derive(Debug)]
+pub(crate) struct TooManyBytes;
+
+impl fmt::Display for TooManyBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("too many bytes")
+    }
+}
+
+impl StdError for TooManyBytes {}
+
 /// Default error for [`crate::prelude::LimitReader`]
 #[allow(clippy::module_name_repetitions)]
 pub type LimitReaderError = Error;
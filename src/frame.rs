@@ -0,0 +1,624 @@
+use crate::error::{ErrorKind, LimitReaderError, UnexpectedEofError};
+use crate::{LimitReader, LimitReaderFallible, LimitReaderResult as Result};
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, Read};
+
+#[cfg(feature = "tokio")]
+use bytes::{Buf, BytesMut};
+#[cfg(feature = "tokio")]
+use tokio_util::codec::Decoder;
+
+// The widest prefix, a varint of a `u64`.
+const MAX_PREFIX_LEN: usize = 10;
+
+/// The byte order of a fixed-width [`LengthPrefix`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    /// Most significant byte first, i.e. network byte order.
+    #[default]
+    Big,
+    /// Least significant byte first.
+    Little,
+}
+
+/// The encoding of the length preceding each frame, which counts the payload only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LengthPrefix {
+    /// A single byte.
+    U8,
+    /// Two bytes.
+    U16(Endian),
+    /// Four bytes.
+    U32(Endian),
+    /// Eight bytes.
+    U64(Endian),
+    /// An unsigned LEB128 varint of up to 10 bytes.
+    Varint,
+    /// A varint of up to 5 bytes holding a `u32`, as written by protobuf's `writeDelimitedTo`.
+    Protobuf,
+}
+
+impl Default for LengthPrefix {
+    fn default() -> Self {
+        Self::U32(Endian::Big)
+    }
+}
+
+impl LengthPrefix {
+    // Decodes the prefix at the start of `buf`, returning the declared length and the width of the prefix, or `None` if more bytes are needed.
+    fn decode(self, buf: &[u8]) -> std::result::Result<Option<(u64, usize)>, FrameViolation> {
+        let (width, endian) = match self {
+            Self::U8 => (1, Endian::Big),
+            Self::U16(endian) => (2, endian),
+            Self::U32(endian) => (4, endian),
+            Self::U64(endian) => (8, endian),
+            Self::Varint => return varint(buf, MAX_PREFIX_LEN),
+            Self::Protobuf => {
+                return match varint(buf, 5)? {
+                    Some((len, _)) if len > u64::from(u32::MAX) => {
+                        Err(FrameViolation::InvalidPrefix)
+                    }
+                    decoded => Ok(decoded),
+                };
+            }
+        };
+        let Some(bytes) = buf.get(..width) else {
+            return Ok(None);
+        };
+        let fold = |len: u64, &b: &u8| len << 8 | u64::from(b);
+        let len = match endian {
+            Endian::Big => bytes.iter().fold(0, fold),
+            Endian::Little => bytes.iter().rev().fold(0, fold),
+        };
+
+        Ok(Some((len, width)))
+    }
+}
+
+fn varint(
+    buf: &[u8],
+    max_width: usize,
+) -> std::result::Result<Option<(u64, usize)>, FrameViolation> {
+    let mut len = 0;
+    for (i, &b) in buf.iter().enumerate().take(max_width) {
+        let bits = u64::from(b & 0x7f);
+        // The tenth byte holds the last bit of a `u64`.
+        if i == MAX_PREFIX_LEN - 1 && bits > 1 {
+            return Err(FrameViolation::InvalidPrefix);
+        }
+        len |= bits << (7 * i);
+        if b & 0x80 == 0 {
+            return Ok(Some((len, i + 1)));
+        }
+    }
+    if buf.len() >= max_width {
+        return Err(FrameViolation::InvalidPrefix);
+    }
+
+    Ok(None)
+}
+
+/// The framing of a length-delimited stream, and the limits enforced on it by a [`FrameReader`] or, with the `tokio` feature, a `FrameCodec`.
+///
+/// Frames are prefixed by a big-endian `u32` and limited to 8 MiB by default.  The other limits are disabled by default.
+#[derive(Debug, Clone)]
+pub struct FrameLimits {
+    prefix: LengthPrefix,
+    max_frame_len: usize,
+    max_stream_len: Option<u64>,
+    max_frames: Option<u64>,
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        Self {
+            prefix: LengthPrefix::default(),
+            max_frame_len: 8 * 1024 * 1024,
+            max_stream_len: None,
+            max_frames: None,
+        }
+    }
+}
+
+impl FrameLimits {
+    /// Create a new [`FrameLimits`] with the default framing and limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the encoding of the length prefix.
+    pub fn prefix(&mut self, prefix: LengthPrefix) -> &mut Self {
+        self.prefix = prefix;
+
+        self
+    }
+
+    /// Limit the payload of each frame to `max_frame_len` bytes.  The declared length is checked before the frame is buffered.
+    pub fn max_frame_len(&mut self, max_frame_len: usize) -> &mut Self {
+        self.max_frame_len = max_frame_len;
+
+        self
+    }
+
+    /// Limit the stream to `max_stream_len` bytes in total, counting prefixes.  A frame whose declared length would exceed it is rejected before it is buffered.
+    pub fn max_stream_len(&mut self, max_stream_len: u64) -> &mut Self {
+        self.max_stream_len = Some(max_stream_len);
+
+        self
+    }
+
+    /// Limit the stream to `max_frames` frames.
+    pub fn max_frames(&mut self, max_frames: u64) -> &mut Self {
+        self.max_frames = Some(max_frames);
+
+        self
+    }
+}
+
+/// The limit violated by a frame; see [`FrameLimitError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FrameViolation {
+    /// The frame declared a payload longer than `max_frame_len` bytes.
+    FrameTooLong {
+        /// The configured maximum frame length.
+        max_frame_len: usize,
+        /// The length declared by the prefix.
+        len: u64,
+    },
+    /// The frame would take the stream beyond `max_stream_len` bytes.
+    StreamTooLong {
+        /// The configured maximum stream length.
+        max_stream_len: u64,
+    },
+    /// The stream held more than `max_frames` frames.
+    TooManyFrames {
+        /// The configured maximum number of frames.
+        max_frames: u64,
+    },
+    /// The varint prefix is longer than allowed, or overflows its type.
+    InvalidPrefix,
+}
+
+/// Structured error raised by a [`FrameReader`] or `FrameCodec`, locating the offending frame.
+///
+/// Retrieve it from a [`LimitReaderError`] of kind [`ErrorKind::FrameLimitExceeded`], or [`ErrorKind::Deserialize`] for [`FrameViolation::InvalidPrefix`], via `LimitReaderError::get_ref()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLimitError {
+    frame_number: u64,
+    offset: u64,
+    violation: FrameViolation,
+}
+
+impl FrameLimitError {
+    /// The 1-based number of the offending frame.
+    #[must_use]
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
+
+    /// Offset of the prefix of the offending frame in the stream.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The limit that was violated.
+    #[must_use]
+    pub fn violation(&self) -> FrameViolation {
+        self.violation
+    }
+}
+
+impl Display for FrameLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "frame {} at offset {} ", self.frame_number, self.offset)?;
+        match self.violation {
+            FrameViolation::FrameTooLong { max_frame_len, len } => write!(
+                f,
+                "declares {len} bytes, exceeding the maximum frame length of {max_frame_len} bytes"
+            ),
+            FrameViolation::StreamTooLong { max_stream_len } => write!(
+                f,
+                "exceeds the maximum stream length of {max_stream_len} bytes"
+            ),
+            FrameViolation::TooManyFrames { max_frames } => {
+                write!(f, "exceeds the maximum of {max_frames} frames")
+            }
+            FrameViolation::InvalidPrefix => f.write_str("has an invalid length prefix"),
+        }
+    }
+}
+
+impl std::error::Error for FrameLimitError {}
+
+// Counts the frames and bytes of a stream against `FrameLimits`.
+#[derive(Debug)]
+struct Frames {
+    limits: FrameLimits,
+    count: u64,
+    offset: u64,
+}
+
+impl Frames {
+    fn new(limits: &FrameLimits) -> Self {
+        Self {
+            limits: limits.clone(),
+            count: 0,
+            offset: 0,
+        }
+    }
+
+    fn decode_prefix(&self, buf: &[u8]) -> Result<Option<(u64, usize)>> {
+        self.limits
+            .prefix
+            .decode(buf)
+            .map_err(|violation| self.violation(violation))
+    }
+
+    // Checks the frame declared by a prefix of `prefix_len` bytes, returning the length of its payload.
+    fn check(&self, len: u64, prefix_len: usize) -> Result<usize> {
+        if let Some(max_frames) = self.limits.max_frames {
+            if self.count >= max_frames {
+                return Err(self.violation(FrameViolation::TooManyFrames { max_frames }));
+            }
+        }
+        let max_frame_len = self.limits.max_frame_len;
+        let Some(payload_len) = usize::try_from(len).ok().filter(|&n| n <= max_frame_len) else {
+            return Err(self.violation(FrameViolation::FrameTooLong { max_frame_len, len }));
+        };
+        if let Some(max_stream_len) = self.limits.max_stream_len {
+            let end = self
+                .offset
+                .saturating_add(prefix_len as u64)
+                .saturating_add(len);
+            if end > max_stream_len {
+                return Err(self.violation(FrameViolation::StreamTooLong { max_stream_len }));
+            }
+        }
+
+        Ok(payload_len)
+    }
+
+    fn commit(&mut self, frame_len: usize) {
+        self.count += 1;
+        self.offset += frame_len as u64;
+    }
+
+    // The stream ended `read` bytes into a frame that needs `expected` bytes.
+    fn unexpected_eof(&self, expected: usize, read: usize) -> LimitReaderError {
+        LimitReaderError::new(
+            ErrorKind::UnexpectedEof,
+            UnexpectedEofError {
+                expected: self.offset + expected as u64,
+                actual: self.offset + read as u64,
+            },
+        )
+    }
+
+    fn violation(&self, violation: FrameViolation) -> LimitReaderError {
+        let kind = match violation {
+            FrameViolation::InvalidPrefix => ErrorKind::Deserialize,
+            _ => ErrorKind::FrameLimitExceeded,
+        };
+
+        LimitReaderError::new(
+            kind,
+            FrameLimitError {
+                frame_number: self.count + 1,
+                offset: self.offset,
+                violation,
+            },
+        )
+    }
+}
+
+impl LimitReader {
+    /// Read length-delimited frames from `source`, decoded as configured and read through a [`crate::LimitReaderFallible`], enforcing `limits` on them; see [`FrameReader`].  Digests, tees and observers do not apply, while [`LimitReader::max_ratio`] and [`LimitReader::min_size`] are enforced as the source is read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be decoded.  Errors reading the frames are returned by [`FrameReader::next_frame`].
+    ///
+    pub fn read_frames<'a>(
+        &self,
+        source: impl Read + 'a,
+        limits: &FrameLimits,
+    ) -> Result<FrameReader<LimitReaderFallible<Box<dyn Read + 'a>>>> {
+        Ok(FrameReader::new(self.strict_reader(source)?, limits))
+    }
+}
+
+/// Reads length-delimited frames from a [`BufRead`], such as the limit adapters, while enforcing [`FrameLimits`].
+///
+/// The length declared by each prefix is checked against the limits before the frame is buffered, so a hostile prefix cannot cause a large allocation.  With the `tokio` feature, `FrameCodec` is the equivalent decoder.
+pub struct FrameReader<R>
+where
+    R: BufRead,
+{
+    reader: R,
+    frames: Frames,
+    done: bool,
+}
+
+impl<R> FrameReader<R>
+where
+    R: BufRead,
+{
+    /// Wrap `reader`, enforcing `limits` on the frames read from it.
+    pub fn new(reader: R, limits: &FrameLimits) -> Self {
+        Self {
+            reader,
+            frames: Frames::new(limits),
+            done: false,
+        }
+    }
+
+    /// Number of frames read so far.
+    #[must_use]
+    pub fn frame_count(&self) -> u64 {
+        self.frames.count
+    }
+
+    /// Read the payload of the next frame, returning `None` at the end of input.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::FrameLimitExceeded`] if a frame violates a limit, [`ErrorKind::Deserialize`] for an invalid prefix, [`ErrorKind::UnexpectedEof`] if the input ends within a frame, or the error of the underlying reader.  No further frames are returned after an error.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let result = self.try_next_frame();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+
+        result
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn try_next_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut prefix = [0; MAX_PREFIX_LEN];
+        let mut prefix_len = 0;
+        let len = loop {
+            if let Some((len, _)) = self.frames.decode_prefix(&prefix[..prefix_len])? {
+                break len;
+            }
+            // Prefixes are read a byte at a time, so that the bytes of the payload are left in the reader.
+            let Some(&b) = self.reader.fill_buf()?.first() else {
+                if prefix_len == 0 {
+                    return Ok(None);
+                }
+                return Err(self.frames.unexpected_eof(prefix_len + 1, prefix_len));
+            };
+            self.reader.consume(1);
+            prefix[prefix_len] = b;
+            prefix_len += 1;
+        };
+
+        let payload_len = self.frames.check(len, prefix_len)?;
+        let mut frame = vec![0; payload_len];
+        let mut read = 0;
+        while read < payload_len {
+            let n = self.reader.read(&mut frame[read..])?;
+            if n == 0 {
+                return Err(self
+                    .frames
+                    .unexpected_eof(prefix_len + payload_len, prefix_len + read));
+            }
+            read += n;
+        }
+        self.frames.commit(prefix_len + payload_len);
+
+        Ok(Some(frame))
+    }
+}
+
+/// Decodes length-delimited frames for `tokio_util::codec::FramedRead`, while enforcing [`FrameLimits`].
+///
+/// The length declared by each prefix is checked against the limits before buffer space is reserved for the frame.  [`FrameReader`] is the blocking equivalent.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct FrameCodec {
+    frames: Frames,
+    // Prefix width and payload length of the frame being received.
+    pending: Option<(usize, usize)>,
+}
+
+#[cfg(feature = "tokio")]
+impl FrameCodec {
+    /// Create a decoder enforcing `limits`.
+    #[must_use]
+    pub fn new(limits: &FrameLimits) -> Self {
+        Self {
+            frames: Frames::new(limits),
+            pending: None,
+        }
+    }
+
+    /// Number of frames decoded so far.
+    #[must_use]
+    pub fn frame_count(&self) -> u64 {
+        self.frames.count
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Decoder for FrameCodec {
+    type Item = BytesMut;
+    type Error = LimitReaderError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>> {
+        let (prefix_len, payload_len) = if let Some(pending) = self.pending {
+            pending
+        } else {
+            let Some((len, prefix_len)) = self.frames.decode_prefix(src)? else {
+                return Ok(None);
+            };
+            let pending = (prefix_len, self.frames.check(len, prefix_len)?);
+            self.pending = Some(pending);
+
+            pending
+        };
+
+        let frame_len = prefix_len + payload_len;
+        if src.len() < frame_len {
+            src.reserve(frame_len - src.len());
+            return Ok(None);
+        }
+        src.advance(prefix_len);
+        let frame = src.split_to(payload_len);
+        self.pending = None;
+        self.frames.commit(frame_len);
+
+        Ok(Some(frame))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>> {
+        if let Some(frame) = self.decode(src)? {
+            return Ok(Some(frame));
+        }
+        if src.is_empty() {
+            return Ok(None);
+        }
+
+        let expected = self
+            .pending
+            .map_or(src.len() + 1, |(prefix_len, payload_len)| {
+                prefix_len + payload_len
+            });
+        Err(self.frames.unexpected_eof(expected, src.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(limits: &FrameLimits, stream: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut reader = LimitReader::new().read_frames(stream, limits)?;
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next_frame()? {
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    fn violation(err: &LimitReaderError) -> FrameLimitError {
+        *err.get_ref().downcast_ref::<FrameLimitError>().unwrap()
+    }
+
+    #[test]
+    fn reads_frames_with_each_prefix() {
+        let mut limits = FrameLimits::new();
+        let cases: [(LengthPrefix, &[u8]); 5] = [
+            (LengthPrefix::default(), b"\0\0\0\x02hi\0\0\0\0"),
+            (LengthPrefix::U16(Endian::Little), b"\x02\0hi\0\0"),
+            (LengthPrefix::U8, b"\x02hi\0"),
+            (LengthPrefix::Varint, b"\x02hi\0"),
+            (LengthPrefix::Protobuf, b"\x02hi\0"),
+        ];
+        for (prefix, stream) in cases {
+            limits.prefix(prefix);
+            assert_eq!(frames(&limits, stream).unwrap(), [&b"hi"[..], b""]);
+        }
+
+        let mut stream = vec![0xac, 0x02];
+        stream.extend_from_slice(&[7; 300]);
+        limits.prefix(LengthPrefix::Varint);
+        assert_eq!(frames(&limits, &stream).unwrap(), [vec![7; 300]]);
+
+        limits.prefix(LengthPrefix::Protobuf);
+        let err = frames(&limits, &[0xff, 0xff, 0xff, 0xff, 0x7f]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Deserialize);
+        assert_eq!(violation(&err).violation(), FrameViolation::InvalidPrefix);
+    }
+
+    #[test]
+    fn rejects_hostile_prefix_before_allocating() {
+        let mut limits = FrameLimits::new();
+        limits.max_frame_len(1024);
+        let err = frames(&limits, b"\0\0\0\x02hi\xff\xff\xff\xff").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FrameLimitExceeded);
+        let frame_err = violation(&err);
+        assert_eq!((frame_err.frame_number(), frame_err.offset()), (2, 6));
+        assert_eq!(
+            frame_err.violation(),
+            FrameViolation::FrameTooLong {
+                max_frame_len: 1024,
+                len: u64::from(u32::MAX)
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Error: frame 2 at offset 6 declares 4294967295 bytes, exceeding the maximum frame length of 1024 bytes"
+        );
+    }
+
+    #[test]
+    fn enforces_stream_limits() {
+        let stream = b"\x01a\x01b\x01c";
+        let mut limits = FrameLimits::new();
+        limits.prefix(LengthPrefix::U8).max_frames(2);
+        let err = frames(&limits, stream).unwrap_err();
+        assert_eq!(
+            violation(&err).violation(),
+            FrameViolation::TooManyFrames { max_frames: 2 }
+        );
+
+        let mut limits = FrameLimits::new();
+        limits.prefix(LengthPrefix::U8).max_stream_len(5);
+        let err = frames(&limits, stream).unwrap_err();
+        assert_eq!(
+            violation(&err).violation(),
+            FrameViolation::StreamTooLong { max_stream_len: 5 }
+        );
+
+        limits = FrameLimits::new();
+        limits.prefix(LengthPrefix::U8);
+        let err = frames(&limits, b"\x01a\x03bc").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        let eof = err.get_ref().downcast_ref::<UnexpectedEofError>().unwrap();
+        assert_eq!((eof.expected(), eof.actual()), (6, 5));
+
+        let mut reader = LimitReader::new()
+            .limit(3)
+            .read_frames(&stream[..], &limits)
+            .unwrap();
+        let err = reader.next_frame().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn codec_decodes_partial_frames() {
+        let mut limits = FrameLimits::new();
+        limits.prefix(LengthPrefix::Varint).max_frame_len(4);
+        let mut codec = FrameCodec::new(&limits);
+        let mut src = BytesMut::new();
+
+        src.extend_from_slice(b"\x03ab");
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(b"c\x00\x05");
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap(), &b"abc"[..]);
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap(), &b""[..]);
+        let err = codec.decode(&mut src).unwrap_err();
+        let frame_err = violation(&err);
+        assert_eq!((frame_err.frame_number(), frame_err.offset()), (3, 5));
+        assert_eq!(codec.frame_count(), 2);
+
+        let mut codec = FrameCodec::new(&limits);
+        let mut src = BytesMut::from(&b"\x02a"[..]);
+        let err = codec.decode_eof(&mut src).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}
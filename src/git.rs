@@ -0,0 +1,409 @@
+//! Reading git objects through the limit adapters.
+//!
+//! Git stores each loose object as a Zlib compressed file at `.git/objects/xx/yyyy...`, holding a `<type> <size>\0` header followed by the object body.  [`LooseObjectReader`] decodes these through the same Zlib path as [`crate::LimitReader`], bounding both the header and the body, enforcing that the body is exactly the declared size and verifying the SHA-1 of the object against its path.
+//!
+//! Git also transfers objects in packfiles, where each entry is Zlib compressed, possibly as a delta against another object.  [`PackReader`] inflates each entry through the limit adapters, bounding the size of each object, the pack as a whole, the number of objects and the depth of delta chains.
+
+pub(crate) use crate::digest::to_hex;
+use crate::error::{ErrorKind, LimitReaderError, UnexpectedEofError};
+use crate::{LimitReader, LimitReaderFallible, LimitReaderResult as Result};
+use sha1::{Digest, Sha1};
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+mod pack;
+
+pub use pack::PackReader;
+
+/// The type of a git object, as named in its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectKind {
+    /// File contents.
+    Blob,
+    /// Directory listing.
+    Tree,
+    /// Commit.
+    Commit,
+    /// Annotated tag.
+    Tag,
+}
+
+impl ObjectKind {
+    /// The name of the type, as used in object headers.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectKind::Blob => "blob",
+            ObjectKind::Tree => "tree",
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tag => "tag",
+        }
+    }
+}
+
+impl FromStr for ObjectKind {
+    type Err = LimitReaderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "blob" => Ok(ObjectKind::Blob),
+            "tree" => Ok(ObjectKind::Tree),
+            "commit" => Ok(ObjectKind::Commit),
+            "tag" => Ok(ObjectKind::Tag),
+            _ => Err(invalid_object(format!("unknown object type `{s}`"))),
+        }
+    }
+}
+
+impl Display for ObjectKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A decoded git object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    kind: ObjectKind,
+    data: Vec<u8>,
+    id: [u8; 20],
+}
+
+impl Object {
+    pub(crate) fn new(kind: ObjectKind, data: Vec<u8>) -> Self {
+        let id = object_id(kind, &data);
+
+        Self { kind, data, id }
+    }
+
+    /// The type of the object.
+    #[must_use]
+    pub fn kind(&self) -> ObjectKind {
+        self.kind
+    }
+
+    /// The object body, excluding the header.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consume the object, returning its body.
+    #[must_use]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The SHA-1 object id.
+    #[must_use]
+    pub fn id(&self) -> [u8; 20] {
+        self.id
+    }
+
+    /// The SHA-1 object id as lowercase hex.
+    #[must_use]
+    pub fn hex_id(&self) -> String {
+        to_hex(&self.id)
+    }
+}
+
+/// Reads loose git objects, i.e. files at `.git/objects/xx/yyyy...`.
+///
+/// The following are enforced, failing with the given [`ErrorKind`]:
+///
+/// - The `<type> <size>\0` header is at most [`LooseObjectReader::max_header_len`] bytes long ([`ErrorKind::InvalidObject`]).
+/// - The declared size is at most [`LooseObjectReader::max_size`] ([`ErrorKind::LimitExceeded`]); this is checked before allocating.
+/// - The body holds exactly the declared size, failing with [`ErrorKind::UnexpectedEof`] if shorter and [`ErrorKind::LimitExceeded`] if longer.
+/// - The SHA-1 of the decoded object matches its path ([`ErrorKind::ObjectIdMismatch`]), unless disabled via [`LooseObjectReader::verify_id`].
+#[derive(Debug, Clone)]
+pub struct LooseObjectReader {
+    max_size: u64,
+    max_header_len: usize,
+    verify_id: bool,
+}
+
+impl Default for LooseObjectReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LooseObjectReader {
+    /// Default maximum size of an object body.
+    pub const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+    /// Default maximum length of an object header, including the terminating `\0`.
+    pub const DEFAULT_MAX_HEADER_LEN: usize = 32;
+
+    /// Create a new [`LooseObjectReader`] with [`LooseObjectReader::DEFAULT_MAX_SIZE`] and [`LooseObjectReader::DEFAULT_MAX_HEADER_LEN`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            max_size: Self::DEFAULT_MAX_SIZE,
+            max_header_len: Self::DEFAULT_MAX_HEADER_LEN,
+            verify_id: true,
+        }
+    }
+
+    /// Set the maximum size of an object body.
+    pub fn max_size(&mut self, max_size: u64) -> &mut Self {
+        self.max_size = max_size;
+
+        self
+    }
+
+    /// Set the maximum length of an object header, including the terminating `\0`.
+    pub fn max_header_len(&mut self, max_header_len: usize) -> &mut Self {
+        self.max_header_len = max_header_len;
+
+        self
+    }
+
+    /// Enable or disable verifying the SHA-1 of objects against their path; enabled by default.
+    pub fn verify_id(&mut self, verify_id: bool) -> &mut Self {
+        self.verify_id = verify_id;
+
+        self
+    }
+
+    /// Read the loose object at `path`, such as `.git/objects/3b/18e512dba79e4c8300dd08aeb37f8e728b8dad`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LimitReaderError`] if the object cannot be read, or violates any of the limits described on [`LooseObjectReader`].
+    pub fn read(&self, path: PathBuf) -> Result<Object> {
+        let expected_id = if self.verify_id {
+            Some(id_from_path(&path)?)
+        } else {
+            None
+        };
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader.enable_decode_zlib();
+        let f = std::fs::File::open(path)?;
+        // Bound the decoded stream as a whole; the header and body are bounded separately below.
+        let limit = (self.max_header_len as u64).saturating_add(self.max_size);
+        let (decoder, _) = limit_reader.decoder(f)?;
+        let mut reader = LimitReaderFallible::new(decoder, limit);
+
+        let (kind, size) = read_header(&mut reader, self.max_header_len)?;
+        if size > self.max_size {
+            return Err(LimitReaderError::new(
+                ErrorKind::LimitExceeded,
+                format!(
+                    "declared object size of {size} bytes exceeds the maximum of {} bytes",
+                    self.max_size
+                ),
+            ));
+        }
+        let data = read_body(&mut reader, size)?;
+
+        let object = Object::new(kind, data);
+        if let Some(expected_id) = expected_id {
+            if object.id != expected_id {
+                return Err(LimitReaderError::new(
+                    ErrorKind::ObjectIdMismatch,
+                    format!(
+                        "object id {} does not match its path {}",
+                        object.hex_id(),
+                        to_hex(&expected_id)
+                    ),
+                ));
+            }
+        }
+
+        Ok(object)
+    }
+}
+
+/// Read a `<type> <size>\0` header of at most `max_header_len` bytes.
+fn read_header(reader: &mut impl BufRead, max_header_len: usize) -> Result<(ObjectKind, u64)> {
+    let mut header = Vec::new();
+    reader
+        .by_ref()
+        .take(max_header_len as u64)
+        .read_until(b'\0', &mut header)?;
+    if header.pop() != Some(b'\0') {
+        return Err(invalid_object(format!(
+            "object header exceeds {max_header_len} bytes or is not terminated"
+        )));
+    }
+
+    let header = std::str::from_utf8(&header)
+        .map_err(|_| invalid_object("object header is not valid UTF-8"))?;
+    let (kind, size) = header
+        .split_once(' ')
+        .ok_or_else(|| invalid_object(format!("malformed object header `{header}`")))?;
+    // Git writes sizes in plain decimal, which `parse` would accept with a sign or leading zeros.
+    let size = Some(size)
+        .filter(|size| size.bytes().all(|b| b.is_ascii_digit()))
+        .filter(|size| *size == "0" || !size.starts_with('0'))
+        .and_then(|size| size.parse().ok())
+        .ok_or_else(|| invalid_object(format!("malformed object size `{size}`")))?;
+
+    Ok((kind.parse()?, size))
+}
+
+/// Read a body of exactly `size` bytes, which must be followed by the end of the stream.
+fn read_body(reader: &mut impl Read, size: u64) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(usize::try_from(size)?);
+    reader.by_ref().take(size).read_to_end(&mut data)?;
+    if (data.len() as u64) < size {
+        return Err(LimitReaderError::new(
+            ErrorKind::UnexpectedEof,
+            UnexpectedEofError {
+                expected: size,
+                actual: data.len() as u64,
+            },
+        ));
+    }
+    if reader.read(&mut [0; 1])? > 0 {
+        return Err(LimitReaderError::new(
+            ErrorKind::LimitExceeded,
+            format!("object body exceeds its declared size of {size} bytes"),
+        ));
+    }
+
+    Ok(data)
+}
+
+/// Compute the id of an object, i.e. the SHA-1 of its header and body.
+pub(crate) fn object_id(kind: ObjectKind, data: &[u8]) -> [u8; 20] {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("{} {}\0", kind, data.len()));
+    hasher.update(data);
+
+    hasher.finalize().into()
+}
+
+/// Parse the object id from a path ending in `xx/yyyy...`.
+fn id_from_path(path: &Path) -> Result<[u8; 20]> {
+    fn name(path: Option<&Path>) -> Option<&str> {
+        path.and_then(Path::file_name).and_then(|s| s.to_str())
+    }
+    let hex = match (name(path.parent()), name(Some(path))) {
+        (Some(dir), Some(file)) => format!("{dir}{file}"),
+        _ => String::new(),
+    };
+
+    from_hex(&hex).ok_or_else(|| {
+        invalid_object(format!(
+            "path {} does not name a loose object",
+            path.display()
+        ))
+    })
+}
+
+fn from_hex(hex: &str) -> Option<[u8; 20]> {
+    // `from_str_radix` would accept a sign, so only hex digits are let through.
+    if hex.len() != 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut id = [0; 20];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    Some(id)
+}
+
+pub(crate) fn invalid_object(message: impl Into<String>) -> LimitReaderError {
+    LimitReaderError::new(ErrorKind::InvalidObject, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    const HELLO_ID: &str = "3b18e512dba79e4c8300dd08aeb37f8e728b8dad";
+
+    fn write_object(dir: &Path, hex: &str, raw: &[u8]) -> PathBuf {
+        let object_dir = dir.join(&hex[..2]);
+        std::fs::create_dir_all(&object_dir).unwrap();
+        let path = object_dir.join(&hex[2..]);
+
+        let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
+        e.write_all(raw).unwrap();
+        File::create(&path)
+            .unwrap()
+            .write_all(&e.finish().unwrap())
+            .unwrap();
+
+        path
+    }
+
+    #[test]
+    fn reads_and_verifies_loose_object() {
+        let dir = tempdir().unwrap();
+        let path = write_object(dir.path(), HELLO_ID, b"blob 12\0hello world\n");
+
+        let object = LooseObjectReader::new().read(path).unwrap();
+        assert_eq!(object.kind(), ObjectKind::Blob);
+        assert_eq!(object.data(), b"hello world\n");
+        assert_eq!(object.hex_id(), HELLO_ID);
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn enforces_declared_size() {
+        let dir = tempdir().unwrap();
+
+        let path = write_object(dir.path(), HELLO_ID, b"blob 14\0hello world\n");
+        let err = LooseObjectReader::new().read(path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        let path = write_object(dir.path(), HELLO_ID, b"blob 10\0hello world\n");
+        let err = LooseObjectReader::new().read(path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+
+        let path = write_object(dir.path(), HELLO_ID, b"blob 12\0hello world\n");
+        let err = LooseObjectReader::new().max_size(8).read(path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_header_and_id() {
+        let dir = tempdir().unwrap();
+
+        let path = write_object(dir.path(), HELLO_ID, b"blob 12\0hello world\n");
+        let err = LooseObjectReader::new()
+            .max_header_len(4)
+            .read(path)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidObject);
+
+        for raw in [
+            &b"blob +12\0hello world\n"[..],
+            b"blob 012\0hello world\n",
+            b"blob  12\0hello world\n",
+            b"blob \0hello world\n",
+        ] {
+            let path = write_object(dir.path(), HELLO_ID, raw);
+            let err = LooseObjectReader::new().read(path).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidObject);
+        }
+
+        let signed_id = "+3b18e512dba79e4c8300dd08aeb37f8e728b8da";
+        let path = write_object(dir.path(), signed_id, b"blob 12\0hello world\n");
+        let err = LooseObjectReader::new().read(path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidObject);
+
+        let other_id = "0000000000000000000000000000000000000000";
+        let path = write_object(dir.path(), other_id, b"blob 12\0hello world\n");
+        let err = LooseObjectReader::new().read(path.clone()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ObjectIdMismatch);
+        assert!(LooseObjectReader::new().verify_id(false).read(path).is_ok());
+
+        dir.close().unwrap();
+    }
+}
@@ -0,0 +1,663 @@
+use super::{invalid_object, to_hex, Object, ObjectKind};
+use crate::error::{ErrorKind, LimitReaderError, UnexpectedEofError};
+use crate::{LimitReaderFallible, LimitReaderResult as Result};
+use flate2::bufread::ZlibDecoder;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::PathBuf;
+
+const OBJ_COMMIT: u8 = 1;
+const OBJ_TREE: u8 = 2;
+const OBJ_BLOB: u8 = 3;
+const OBJ_TAG: u8 = 4;
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+
+/// Reads git packfiles (`.pack`), such as those received from untrusted remotes.
+///
+/// Each entry is inflated through a [`LimitReaderFallible`] bounded by its declared size, and deltas are resolved against their (earlier) base objects.  The following are enforced, failing with [`ErrorKind::LimitExceeded`]:
+///
+/// - The number of objects declared by the pack header is at most [`PackReader::max_objects`]; this is checked before reading any entry.
+/// - The size declared by each entry, and the result size declared by each delta, is at most [`PackReader::max_object_size`]; this is checked before inflating.
+/// - The sum of all inflated entries and resolved deltas is at most [`PackReader::max_total_size`].
+/// - The OFS/REF delta chain of each object is at most [`PackReader::max_delta_depth`] deltas deep.
+/// - Delta instructions never produce more than the declared result size.
+///
+/// Malformed entries, deltas and a mismatching trailing checksum fail with [`ErrorKind::InvalidObject`].
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_field_names)]
+pub struct PackReader {
+    max_object_size: u64,
+    max_total_size: u64,
+    max_objects: u32,
+    max_delta_depth: usize,
+}
+
+impl Default for PackReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PackReader {
+    /// Default maximum size of a single inflated object.
+    pub const DEFAULT_MAX_OBJECT_SIZE: u64 = 64 * 1024 * 1024;
+
+    /// Default maximum size of all inflated objects in a pack.
+    pub const DEFAULT_MAX_TOTAL_SIZE: u64 = 1024 * 1024 * 1024;
+
+    /// Default maximum number of objects in a pack.
+    pub const DEFAULT_MAX_OBJECTS: u32 = 1024 * 1024;
+
+    /// Default maximum delta chain depth, matching the default `--depth` of `git repack`.
+    pub const DEFAULT_MAX_DELTA_DEPTH: usize = 50;
+
+    /// Create a new [`PackReader`] with the `DEFAULT_*` limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            max_object_size: Self::DEFAULT_MAX_OBJECT_SIZE,
+            max_total_size: Self::DEFAULT_MAX_TOTAL_SIZE,
+            max_objects: Self::DEFAULT_MAX_OBJECTS,
+            max_delta_depth: Self::DEFAULT_MAX_DELTA_DEPTH,
+        }
+    }
+
+    /// Set the maximum size of a single inflated object.
+    pub fn max_object_size(&mut self, max_object_size: u64) -> &mut Self {
+        self.max_object_size = max_object_size;
+
+        self
+    }
+
+    /// Set the maximum size of all inflated objects in a pack.
+    pub fn max_total_size(&mut self, max_total_size: u64) -> &mut Self {
+        self.max_total_size = max_total_size;
+
+        self
+    }
+
+    /// Set the maximum number of objects in a pack.
+    pub fn max_objects(&mut self, max_objects: u32) -> &mut Self {
+        self.max_objects = max_objects;
+
+        self
+    }
+
+    /// Set the maximum delta chain depth; `0` rejects all deltas.
+    pub fn max_delta_depth(&mut self, max_delta_depth: usize) -> &mut Self {
+        self.max_delta_depth = max_delta_depth;
+
+        self
+    }
+
+    /// Read the packfile at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LimitReaderError`] if the pack cannot be read, or violates any of the limits described on [`PackReader`].
+    pub fn read_file(&self, path: PathBuf) -> Result<Vec<Object>> {
+        let f = std::fs::File::open(path)?;
+
+        self.read(BufReader::new(f))
+    }
+
+    /// Read a packfile from `reader`, returning its objects in pack order with all deltas resolved.
+    ///
+    /// REF deltas must refer to an object earlier in the same pack, i.e. thin packs are not supported.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LimitReaderError`] if the pack cannot be read, or violates any of the limits described on [`PackReader`].
+    pub fn read(&self, reader: impl BufRead) -> Result<Vec<Object>> {
+        let mut input = PackInput::new(reader);
+
+        let mut header = [0; 12];
+        input.read_exact(&mut header)?;
+        if &header[..4] != b"PACK" {
+            return Err(invalid_object("missing pack signature"));
+        }
+        let version = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        if version != 2 && version != 3 {
+            return Err(invalid_object(format!(
+                "unsupported pack version {version}"
+            )));
+        }
+        let count = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+        if count > self.max_objects {
+            return Err(limit_exceeded(format!(
+                "pack declares {count} objects, exceeding the maximum of {}",
+                self.max_objects
+            )));
+        }
+
+        let mut pack = Pack::default();
+        for _ in 0..count {
+            self.read_entry(&mut input, &mut pack)?;
+        }
+
+        let checksum: [u8; 20] = input.hasher.clone().finalize().into();
+        let mut trailer = [0; 20];
+        input.read_exact(&mut trailer)?;
+        if checksum != trailer {
+            return Err(invalid_object(format!(
+                "pack checksum {} does not match its trailer {}",
+                to_hex(&checksum),
+                to_hex(&trailer)
+            )));
+        }
+
+        Ok(pack.objects)
+    }
+
+    fn read_entry<R: BufRead>(&self, input: &mut PackInput<R>, pack: &mut Pack) -> Result<()> {
+        let offset = input.pos;
+        let (type_code, size) = read_entry_header(input)?;
+        if size > self.max_object_size {
+            return Err(limit_exceeded(format!(
+                "object at offset {offset} declares {size} bytes, exceeding the maximum of {}",
+                self.max_object_size
+            )));
+        }
+
+        let base = match type_code {
+            OBJ_OFS_DELTA => {
+                let distance = read_ofs_distance(input)?;
+                let base_offset = offset
+                    .checked_sub(distance)
+                    .and_then(|base_offset| pack.by_offset.get(&base_offset))
+                    .ok_or_else(|| {
+                        invalid_object(format!(
+                            "delta at offset {offset} refers to a missing base object"
+                        ))
+                    })?;
+                Some(*base_offset)
+            }
+            OBJ_REF_DELTA => {
+                let mut id = [0; 20];
+                input.read_exact(&mut id)?;
+                let base = pack.by_id.get(&id).ok_or_else(|| {
+                    invalid_object(format!(
+                        "delta at offset {offset} refers to missing base object {}",
+                        to_hex(&id)
+                    ))
+                })?;
+                Some(*base)
+            }
+            _ => None,
+        };
+
+        let depth = base.map_or(0, |base| pack.depths[base] + 1);
+        if depth > self.max_delta_depth {
+            return Err(limit_exceeded(format!(
+                "delta chain of object at offset {offset} exceeds the maximum depth of {}",
+                self.max_delta_depth
+            )));
+        }
+
+        pack.charge(size, self.max_total_size)?;
+        let data = inflate(input, size)?;
+
+        let object = if let Some(base) = base {
+            let delta = Delta::parse(&data, self.max_object_size)?;
+            // Charge the declared result size before allocating the result.
+            pack.charge(delta.result_size as u64, self.max_total_size)?;
+            let base = &pack.objects[base];
+
+            Object::new(base.kind(), delta.apply(base.data())?)
+        } else {
+            let kind = match type_code {
+                OBJ_COMMIT => ObjectKind::Commit,
+                OBJ_TREE => ObjectKind::Tree,
+                OBJ_BLOB => ObjectKind::Blob,
+                OBJ_TAG => ObjectKind::Tag,
+                _ => {
+                    return Err(invalid_object(format!(
+                        "object at offset {offset} has invalid type {type_code}"
+                    )))
+                }
+            };
+
+            Object::new(kind, data)
+        };
+
+        pack.insert(offset, object, depth);
+
+        Ok(())
+    }
+}
+
+/// Objects read so far, indexed for resolving deltas.
+#[derive(Default)]
+struct Pack {
+    objects: Vec<Object>,
+    depths: Vec<usize>,
+    by_offset: HashMap<u64, usize>,
+    by_id: HashMap<[u8; 20], usize>,
+    total_size: u64,
+}
+
+impl Pack {
+    fn charge(&mut self, size: u64, max_total_size: u64) -> Result<()> {
+        self.total_size = self.total_size.saturating_add(size);
+        if self.total_size > max_total_size {
+            return Err(limit_exceeded(format!(
+                "pack exceeds the maximum total size of {max_total_size} bytes"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn insert(&mut self, offset: u64, object: Object, depth: usize) {
+        let index = self.objects.len();
+        self.by_offset.insert(offset, index);
+        self.by_id.insert(object.id(), index);
+        self.depths.push(depth);
+        self.objects.push(object);
+    }
+}
+
+/// Tracks the position within the pack and its SHA-1 checksum, over all consumed bytes.
+struct PackInput<R> {
+    inner: R,
+    pos: u64,
+    hasher: Sha1,
+}
+
+impl<R: BufRead> PackInput<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            pos: 0,
+            hasher: Sha1::new(),
+        }
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        let mut byte = [0; 1];
+        self.read_exact(&mut byte)?;
+
+        Ok(byte[0])
+    }
+}
+
+impl<R: BufRead> Read for PackInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for PackInput<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // NOTE: The bytes being consumed are still buffered, so this does not perform any I/O.
+        if let Ok(buf) = self.inner.fill_buf() {
+            self.hasher.update(&buf[..amt.min(buf.len())]);
+        }
+        self.pos += amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+/// Read the type and size of an entry.
+fn read_entry_header<R: BufRead>(input: &mut PackInput<R>) -> Result<(u8, u64)> {
+    let mut byte = input.read_u8()?;
+    let type_code = (byte >> 4) & 0b111;
+    let mut size = u64::from(byte & 0b1111);
+    let mut shift = 4;
+    while byte & 0x80 != 0 {
+        if shift > 57 {
+            return Err(invalid_object("object size overflows"));
+        }
+        byte = input.read_u8()?;
+        size |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+    }
+
+    Ok((type_code, size))
+}
+
+/// Read the distance of an OFS delta to its base object.
+fn read_ofs_distance<R: BufRead>(input: &mut PackInput<R>) -> Result<u64> {
+    let mut byte = input.read_u8()?;
+    let mut distance = u64::from(byte & 0x7f);
+    while byte & 0x80 != 0 {
+        byte = input.read_u8()?;
+        distance = distance
+            .checked_add(1)
+            .and_then(|d| d.checked_mul(128))
+            .ok_or_else(|| invalid_object("delta base offset overflows"))?
+            | u64::from(byte & 0x7f);
+    }
+
+    Ok(distance)
+}
+
+/// Inflate a Zlib stream of exactly `size` bytes, consuming only its compressed bytes from `input`.
+fn inflate<R: BufRead>(input: &mut PackInput<R>, size: u64) -> Result<Vec<u8>> {
+    let mut reader = LimitReaderFallible::new(ZlibDecoder::new(input), size);
+    let mut data = Vec::new();
+    reader
+        .read_to_end(&mut data)
+        .map_err(|err| match err.kind() {
+            // Raised by the decoder for a corrupt Zlib stream.
+            io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => {
+                LimitReaderError::from_io(err, ErrorKind::InvalidObject)
+            }
+            _ => LimitReaderError::from_io(err, ErrorKind::IoError),
+        })?;
+    if (data.len() as u64) < size {
+        return Err(LimitReaderError::new(
+            ErrorKind::UnexpectedEof,
+            UnexpectedEofError {
+                expected: size,
+                actual: data.len() as u64,
+            },
+        ));
+    }
+
+    Ok(data)
+}
+
+/// Read a size from a delta header.
+fn read_delta_size(delta: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut size = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *delta
+            .get(*pos)
+            .ok_or_else(|| invalid_object("truncated delta header"))?;
+        *pos += 1;
+        if shift > 57 {
+            return Err(invalid_object("delta size overflows"));
+        }
+        size |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+/// A delta, as declared by its header.
+struct Delta<'a> {
+    base_size: u64,
+    result_size: usize,
+    instructions: &'a [u8],
+}
+
+impl<'a> Delta<'a> {
+    /// Parse the header of `delta`, rejecting result sizes above `max_object_size`.
+    fn parse(delta: &'a [u8], max_object_size: u64) -> Result<Self> {
+        let mut pos = 0;
+        let base_size = read_delta_size(delta, &mut pos)?;
+        let result_size = read_delta_size(delta, &mut pos)?;
+        if result_size > max_object_size {
+            return Err(limit_exceeded(format!(
+                "delta result declares {result_size} bytes, exceeding the maximum of {max_object_size}"
+            )));
+        }
+
+        Ok(Self {
+            base_size,
+            result_size: usize::try_from(result_size)?,
+            instructions: &delta[pos..],
+        })
+    }
+
+    /// Apply the delta to `base`, rejecting instructions that would produce more than the declared result size.
+    fn apply(&self, base: &[u8]) -> Result<Vec<u8>> {
+        if self.base_size != base.len() as u64 {
+            return Err(invalid_object(format!(
+                "delta base size {} does not match the base object size {}",
+                self.base_size,
+                base.len()
+            )));
+        }
+
+        apply_delta(base, self.instructions, self.result_size)
+    }
+}
+
+/// Apply the `delta` instructions to `base`, rejecting instructions that would produce more than `result_size` bytes.
+fn apply_delta(base: &[u8], delta: &[u8], result_size: usize) -> Result<Vec<u8>> {
+    let mut pos = 0;
+    let byte = |pos: &mut usize| -> Result<usize> {
+        let byte = delta
+            .get(*pos)
+            .ok_or_else(|| invalid_object("truncated delta instruction"))?;
+        *pos += 1;
+
+        Ok(usize::from(*byte))
+    };
+    let mut result = Vec::with_capacity(result_size);
+    while pos < delta.len() {
+        let op = byte(&mut pos)?;
+        let chunk = if op & 0x80 != 0 {
+            // Copy from the base object.
+            let mut offset = 0;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= byte(&mut pos)? << (8 * i);
+                }
+            }
+            let mut size = 0;
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    size |= byte(&mut pos)? << (8 * i);
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            offset
+                .checked_add(size)
+                .and_then(|end| base.get(offset..end))
+                .ok_or_else(|| invalid_object("delta copies beyond its base object"))?
+        } else if op != 0 {
+            // Insert literal bytes from the delta.
+            let chunk = delta
+                .get(pos..pos + op)
+                .ok_or_else(|| invalid_object("truncated delta instruction"))?;
+            pos += op;
+            chunk
+        } else {
+            return Err(invalid_object("reserved delta instruction"));
+        };
+
+        if result.len() + chunk.len() > result_size {
+            return Err(limit_exceeded(format!(
+                "delta result exceeds its declared size of {result_size} bytes"
+            )));
+        }
+        result.extend_from_slice(chunk);
+    }
+    if result.len() != result_size {
+        return Err(invalid_object(format!(
+            "delta result of {} bytes does not match its declared size of {result_size} bytes",
+            result.len()
+        )));
+    }
+
+    Ok(result)
+}
+
+fn limit_exceeded(message: String) -> LimitReaderError {
+    LimitReaderError::new(ErrorKind::LimitExceeded, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::object_id;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const HELLO: &[u8] = b"hello world\n";
+
+    fn entry_header(type_code: u8, size: usize) -> Vec<u8> {
+        let mut header = vec![(type_code << 4) | (size & 0b1111) as u8];
+        let mut size = size >> 4;
+        while size > 0 {
+            *header.last_mut().unwrap() |= 0x80;
+            header.push((size & 0x7f) as u8);
+            size >>= 7;
+        }
+
+        header
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
+        e.write_all(data).unwrap();
+
+        e.finish().unwrap()
+    }
+
+    // Copies "hello " from the base object, then inserts "rust\n".
+    fn delta(result_size: u8) -> Vec<u8> {
+        let mut delta = vec![HELLO.len() as u8, result_size, 0x80 | 0x10, 6, 5];
+        delta.extend_from_slice(b"rust\n");
+
+        delta
+    }
+
+    fn pack(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut pack = b"PACK".to_vec();
+        pack.extend_from_slice(&2_u32.to_be_bytes());
+        pack.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        for entry in entries {
+            pack.extend_from_slice(entry);
+        }
+        let checksum: [u8; 20] = Sha1::digest(&pack).into();
+        pack.extend_from_slice(&checksum);
+
+        pack
+    }
+
+    fn blob_entry() -> Vec<u8> {
+        let mut entry = entry_header(OBJ_BLOB, HELLO.len());
+        entry.extend(deflate(HELLO));
+
+        entry
+    }
+
+    fn ofs_delta_entry(distance: u8, delta: &[u8]) -> Vec<u8> {
+        let mut entry = entry_header(OBJ_OFS_DELTA, delta.len());
+        entry.push(distance);
+        entry.extend(deflate(delta));
+
+        entry
+    }
+
+    #[test]
+    fn reads_objects_and_resolves_deltas() {
+        let blob = blob_entry();
+        let ofs_delta = ofs_delta_entry(blob.len() as u8, &delta(11));
+        let mut ref_delta = entry_header(OBJ_REF_DELTA, delta(11).len());
+        ref_delta.extend(object_id(ObjectKind::Blob, HELLO));
+        ref_delta.extend(deflate(&delta(11)));
+
+        let pack = pack(&[blob, ofs_delta, ref_delta]);
+        let objects = PackReader::new().read(&pack[..]).unwrap();
+
+        assert_eq!(objects.len(), 3);
+        assert_eq!(objects[0].data(), HELLO);
+        assert_eq!(
+            objects[0].hex_id(),
+            "3b18e512dba79e4c8300dd08aeb37f8e728b8dad"
+        );
+        assert_eq!(objects[1].kind(), ObjectKind::Blob);
+        assert_eq!(objects[1].data(), b"hello rust\n");
+        assert_eq!(objects[2].data(), b"hello rust\n");
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let blob = blob_entry();
+        let ofs_delta = ofs_delta_entry(blob.len() as u8, &delta(11));
+        let pack = pack(&[blob.clone(), ofs_delta]);
+
+        let kind = |reader: &PackReader| reader.read(&pack[..]).unwrap_err().kind();
+        assert_eq!(
+            kind(PackReader::new().max_delta_depth(0)),
+            ErrorKind::LimitExceeded
+        );
+        assert_eq!(
+            kind(PackReader::new().max_objects(1)),
+            ErrorKind::LimitExceeded
+        );
+        assert_eq!(
+            kind(PackReader::new().max_object_size(8)),
+            ErrorKind::LimitExceeded
+        );
+        assert_eq!(
+            kind(PackReader::new().max_total_size(20)),
+            ErrorKind::LimitExceeded
+        );
+    }
+
+    #[test]
+    fn rejects_delta_exceeding_declared_size() {
+        let blob = blob_entry();
+        let ofs_delta = ofs_delta_entry(blob.len() as u8, &delta(8));
+        let pack = pack(&[blob, ofs_delta]);
+
+        let err = PackReader::new().read(&pack[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        assert_eq!(
+            "Error: delta result exceeds its declared size of 8 bytes",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn charges_delta_result_before_allocating() {
+        let blob = blob_entry();
+        // Declares a result of 1 MiB.
+        let mut delta = vec![HELLO.len() as u8, 0x80, 0x80, 0x40, 0x80 | 0x10, 6, 5];
+        delta.extend_from_slice(b"rust\n");
+        let ofs_delta = ofs_delta_entry(blob.len() as u8, &delta);
+        let pack = pack(&[blob, ofs_delta]);
+
+        let err = PackReader::new()
+            .max_total_size(1024)
+            .read(&pack[..])
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        assert_eq!(
+            "Error: pack exceeds the maximum total size of 1024 bytes",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_entry() {
+        let mut entry = entry_header(OBJ_BLOB, HELLO.len());
+        entry.extend_from_slice(b"not zlib at all");
+        let pack = pack(&[entry]);
+
+        let err = PackReader::new().read(&pack[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidObject);
+    }
+
+    #[test]
+    fn rejects_corrupt_checksum() {
+        let mut pack = pack(&[blob_entry()]);
+        *pack.last_mut().unwrap() ^= 0xff;
+
+        let err = PackReader::new().read(&pack[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidObject);
+    }
+}
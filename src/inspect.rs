@@ -0,0 +1,210 @@
+use crate::error::{ErrorKind, LimitReaderError};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+/// A decoded chunk accepted by a limit adapter, as seen by an [`Inspector`].
+///
+/// To match content spanning chunk boundaries, the chunk is preceded by up to `context_len` bytes of the previous chunks, as set with `inspect`.
+#[derive(Debug, Clone, Copy)]
+pub struct Chunk<'a> {
+    offset: u64,
+    context_len: usize,
+    window: &'a [u8],
+}
+
+impl<'a> Chunk<'a> {
+    /// Offset of the chunk's first byte in the stream.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The bytes of the chunk.
+    #[must_use]
+    pub fn data(&self) -> &'a [u8] {
+        &self.window[self.context_len..]
+    }
+
+    /// The bytes preceding the chunk.
+    #[must_use]
+    pub fn context(&self) -> &'a [u8] {
+        &self.window[..self.context_len]
+    }
+
+    /// The context followed by the chunk.
+    #[must_use]
+    pub fn window(&self) -> &'a [u8] {
+        self.window
+    }
+
+    /// Offset of the window's first byte in the stream.
+    #[must_use]
+    pub fn window_offset(&self) -> u64 {
+        self.offset - self.context_len as u64
+    }
+}
+
+/// Structured error for [`ErrorKind::Rejected`], raised when an [`Inspector`] rejects the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedError {
+    offset: u64,
+    reason: String,
+}
+
+impl RejectedError {
+    /// Reject the stream because of the content at `offset`.
+    pub fn new(offset: u64, reason: impl Into<String>) -> Self {
+        Self {
+            offset,
+            reason: reason.into(),
+        }
+    }
+
+    /// Offset in the stream of the rejected content.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Why the stream was rejected.
+    #[must_use]
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl Display for RejectedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "rejected at offset {}: {}", self.offset, self.reason)
+    }
+}
+
+impl std::error::Error for RejectedError {}
+
+/// Inspects each decoded chunk accepted by a limit adapter, e.g. to reject executables or binary data in text uploads as soon as they appear.
+pub trait Inspector: Send {
+    /// Inspect the next chunk of the stream.
+    ///
+    /// # Errors
+    ///
+    /// Return a [`RejectedError`] to abort the read with [`ErrorKind::Rejected`].
+    fn inspect(&mut self, chunk: &Chunk<'_>) -> Result<(), RejectedError>;
+}
+
+impl<F> Inspector for F
+where
+    F: FnMut(&Chunk<'_>) -> Result<(), RejectedError> + Send,
+{
+    fn inspect(&mut self, chunk: &Chunk<'_>) -> Result<(), RejectedError> {
+        self(chunk)
+    }
+}
+
+/// An [`Inspector`], along with the stream position and context it needs.
+pub(crate) struct Inspection {
+    inspector: Box<dyn Inspector>,
+    context_len: usize,
+    offset: u64,
+    window: Vec<u8>,
+}
+
+impl Inspection {
+    pub(crate) fn new(inspector: impl Inspector + 'static, context_len: usize) -> Self {
+        Self {
+            inspector: Box::new(inspector),
+            context_len,
+            offset: 0,
+            window: Vec::new(),
+        }
+    }
+
+    /// Pass `data` to the inspector, then keep its tail as context for the next chunk.
+    pub(crate) fn inspect(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let context_len = self.window.len();
+        self.window.extend_from_slice(data);
+
+        let chunk = Chunk {
+            offset: self.offset,
+            context_len,
+            window: &self.window,
+        };
+        self.inspector
+            .inspect(&chunk)
+            .map_err(|err| LimitReaderError::new(ErrorKind::Rejected, err))?;
+
+        self.offset += data.len() as u64;
+        let excess = self.window.len().saturating_sub(self.context_len);
+        self.window.drain(..excess);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LimitReaderFallible, LimitReaderInfallible};
+    use std::io::Read;
+
+    // Yields at most 3 bytes per read.
+    struct Chunked<'a>(&'a [u8]);
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+
+            Ok(n)
+        }
+    }
+
+    fn reject_elf(chunk: &Chunk<'_>) -> Result<(), RejectedError> {
+        const MAGIC: &[u8] = b"\x7fELF";
+
+        match chunk.window().windows(MAGIC.len()).position(|w| w == MAGIC) {
+            Some(pos) => Err(RejectedError::new(
+                chunk.window_offset() + pos as u64,
+                "ELF executable",
+            )),
+            None => Ok(()),
+        }
+    }
+
+    #[test]
+    fn rejects_signature_spanning_chunks() {
+        let mut reader = LimitReaderFallible::new(Chunked(b"hello\x7fELF\x02\x01"), 100);
+        reader.inspect(reject_elf, 3);
+
+        let err = LimitReaderError::from(reader.read_to_end(&mut Vec::new()).unwrap_err());
+        assert_eq!(err.kind(), ErrorKind::Rejected);
+        let rejected = err.get_ref().downcast_ref::<RejectedError>().unwrap();
+        assert_eq!(rejected.offset(), 5);
+        assert_eq!(rejected.reason(), "ELF executable");
+    }
+
+    #[test]
+    fn rejects_nul_bytes_in_text() {
+        let reject_nul = |chunk: &Chunk<'_>| match chunk.data().iter().position(|&b| b == 0) {
+            Some(pos) => Err(RejectedError::new(chunk.offset() + pos as u64, "NUL byte")),
+            None => Ok(()),
+        };
+
+        let mut reader = LimitReaderInfallible::new(Chunked(b"Mike was here."), 100);
+        reader.inspect(reject_nul, 0);
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "Mike was here.");
+
+        let mut reader = LimitReaderInfallible::new(Chunked(b"Mike\0was here."), 100);
+        reader.inspect(reject_nul, 0);
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: rejected at offset 4: NUL byte".to_string()
+        );
+    }
+}
@@ -0,0 +1,716 @@
+use crate::error::{ErrorKind, LimitReaderError};
+use crate::LimitReaderResult as Result;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Read};
+
+/// Structural limits enforced on a JSON document by a [`JsonReader`].
+///
+/// All limits are disabled by default.
+#[derive(Debug, Default, Clone)]
+#[allow(clippy::struct_field_names)]
+pub struct JsonLimits {
+    max_depth: Option<usize>,
+    max_len: Option<usize>,
+    max_string_len: Option<usize>,
+    max_tokens: Option<u64>,
+}
+
+impl JsonLimits {
+    /// Create a new [`JsonLimits`] with no limits configured.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the nesting of arrays and objects to `max_depth` levels; a top-level array is at depth 1.
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = Some(max_depth);
+
+        self
+    }
+
+    /// Limit each array to `max_len` elements, and each object to `max_len` members.
+    pub fn max_len(&mut self, max_len: usize) -> &mut Self {
+        self.max_len = Some(max_len);
+
+        self
+    }
+
+    /// Limit each string, including object keys, to `max_string_len` bytes as encoded in the document.
+    pub fn max_string_len(&mut self, max_string_len: usize) -> &mut Self {
+        self.max_string_len = Some(max_string_len);
+
+        self
+    }
+
+    /// Limit the document to `max_tokens` values and keys in total, counting each array and object as one.
+    pub fn max_tokens(&mut self, max_tokens: u64) -> &mut Self {
+        self.max_tokens = Some(max_tokens);
+
+        self
+    }
+}
+
+/// The limit violated by a JSON document; see [`JsonLimitError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JsonViolation {
+    /// Arrays and objects were nested more than `max_depth` levels deep.
+    TooDeep {
+        /// The configured maximum depth.
+        max_depth: usize,
+    },
+    /// An array or object held more than `max_len` elements or members.
+    TooLong {
+        /// The configured maximum length.
+        max_len: usize,
+    },
+    /// A string or key was longer than `max_string_len` bytes.
+    StringTooLong {
+        /// The configured maximum string length.
+        max_string_len: usize,
+    },
+    /// The document held more than `max_tokens` values and keys.
+    TooManyTokens {
+        /// The configured maximum number of tokens.
+        max_tokens: u64,
+    },
+    /// The document is not well-formed JSON, e.g. because it was cut short.
+    Malformed,
+}
+
+/// Structured error raised by a [`JsonReader`], locating the offending part of the document by its JSON pointer (RFC 6901).
+///
+/// Retrieve it from a [`LimitReaderError`] of kind [`ErrorKind::JsonLimitExceeded`], or [`ErrorKind::Deserialize`] for [`JsonViolation::Malformed`], via `LimitReaderError::get_ref()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonLimitError {
+    pointer: String,
+    offset: u64,
+    violation: JsonViolation,
+}
+
+impl JsonLimitError {
+    /// JSON pointer to the offending value, or to the array or object exceeding `max_len`; empty for the whole document.
+    #[must_use]
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// Offset of the offending byte in the decoded stream.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The limit that was violated.
+    #[must_use]
+    pub fn violation(&self) -> JsonViolation {
+        self.violation
+    }
+}
+
+impl Display for JsonLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` at offset {} ", self.pointer, self.offset)?;
+        match self.violation {
+            JsonViolation::TooDeep { max_depth } => {
+                write!(f, "exceeds the maximum depth of {max_depth}")
+            }
+            JsonViolation::TooLong { max_len } => {
+                write!(f, "exceeds the maximum length of {max_len}")
+            }
+            JsonViolation::StringTooLong { max_string_len } => write!(
+                f,
+                "exceeds the maximum string length of {max_string_len} bytes"
+            ),
+            JsonViolation::TooManyTokens { max_tokens } => {
+                write!(f, "exceeds the maximum of {max_tokens} tokens")
+            }
+            JsonViolation::Malformed => f.write_str("is malformed"),
+        }
+    }
+}
+
+impl std::error::Error for JsonLimitError {}
+
+/// Validates the structure of a JSON document read through it against [`JsonLimits`], e.g. ahead of `serde_json`.
+///
+/// Bytes are passed on as read, but a chunk violating a limit is never passed on: the read fails with a [`JsonLimitError`] instead.  Only the state of the open arrays and objects is kept, so memory use is bounded by the depth of the document rather than its size.  The key of each open object is kept for the pointer of a [`JsonLimitError`], cut short after 1 KiB.
+pub struct JsonReader<R> {
+    reader: R,
+    validator: Validator,
+}
+
+impl<R: Read> JsonReader<R> {
+    /// Wrap `reader`, enforcing `limits` on the document read from it.
+    pub fn new(reader: R, limits: &JsonLimits) -> Self {
+        Self {
+            reader,
+            validator: Validator::new(limits.clone()),
+        }
+    }
+
+    /// Read the rest of the document, only to validate it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::JsonLimitExceeded`] or [`ErrorKind::Deserialize`] if the document violates a limit or is malformed, or the error of the underlying reader.
+    pub fn validate(mut self) -> Result<()> {
+        io::copy(&mut self, &mut io::sink())
+            .map_err(|err| LimitReaderError::from_io(err, ErrorKind::ReadError))?;
+
+        Ok(())
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Read for JsonReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        if n == 0 {
+            self.validator.finish()?;
+        } else {
+            self.validator.feed(&buf[..n])?;
+        }
+
+        Ok(n)
+    }
+}
+
+// What the validator expects next, outside of a string, number or literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Value,
+    ValueOrClose,
+    Key,
+    KeyOrClose,
+    Colon,
+    CommaOrClose,
+    End,
+}
+
+#[derive(Debug)]
+enum Frame {
+    Array { len: usize },
+    Object { len: usize, key: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Lexeme {
+    None,
+    String {
+        key: bool,
+        len: usize,
+        escape: Escape,
+    },
+    Number(Number),
+    Literal {
+        word: &'static [u8],
+        pos: usize,
+    },
+}
+
+// The part of a number read so far, as per the grammar of RFC 8259.
+#[derive(Debug, Clone, Copy)]
+enum Number {
+    Minus,
+    Zero,
+    Integer,
+    Point,
+    Fraction,
+    Exponent,
+    ExponentSign,
+    ExponentDigits,
+}
+
+impl Number {
+    fn start(b: u8) -> Self {
+        match b {
+            b'-' => Number::Minus,
+            b'0' => Number::Zero,
+            _ => Number::Integer,
+        }
+    }
+
+    // The part read once `b` is appended, unless `b` cannot continue the number.
+    fn next(self, b: u8) -> Option<Self> {
+        match (self, b) {
+            (Number::Minus, b'0') => Some(Number::Zero),
+            (Number::Minus, b'1'..=b'9') | (Number::Integer, b'0'..=b'9') => Some(Number::Integer),
+            (Number::Zero | Number::Integer, b'.') => Some(Number::Point),
+            (Number::Point | Number::Fraction, b'0'..=b'9') => Some(Number::Fraction),
+            (Number::Zero | Number::Integer | Number::Fraction, b'e' | b'E') => {
+                Some(Number::Exponent)
+            }
+            (Number::Exponent, b'+' | b'-') => Some(Number::ExponentSign),
+            (Number::Exponent | Number::ExponentSign | Number::ExponentDigits, b'0'..=b'9') => {
+                Some(Number::ExponentDigits)
+            }
+            _ => None,
+        }
+    }
+
+    // Whether the number may end here.
+    fn complete(self) -> bool {
+        matches!(
+            self,
+            Number::Zero | Number::Integer | Number::Fraction | Number::ExponentDigits
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Escape {
+    None,
+    Backslash,
+    Unicode { digits: u8, code: u32 },
+}
+
+// Bytes of each key kept for the pointer of a violation.
+const MAX_KEY_LEN: usize = 1024;
+
+// A streaming JSON tokenizer, keeping track of the open arrays and objects.
+struct Validator {
+    limits: JsonLimits,
+    stack: Vec<Frame>,
+    expect: Expect,
+    lexeme: Lexeme,
+    tokens: u64,
+    offset: u64,
+}
+
+impl Validator {
+    fn new(limits: JsonLimits) -> Self {
+        Self {
+            limits,
+            stack: Vec::new(),
+            expect: Expect::Value,
+            lexeme: Lexeme::None,
+            tokens: 0,
+            offset: 0,
+        }
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Result<()> {
+        for &b in data {
+            self.byte(b)?;
+            self.offset += 1;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Lexeme::Number(number) = self.lexeme {
+            if number.complete() {
+                self.lexeme = Lexeme::None;
+                self.end_value();
+            }
+        }
+        if !matches!(self.lexeme, Lexeme::None) || self.expect != Expect::End {
+            return Err(self.violation(JsonViolation::Malformed, true));
+        }
+
+        Ok(())
+    }
+
+    fn byte(&mut self, b: u8) -> Result<()> {
+        match self.lexeme {
+            Lexeme::String { key, len, escape } => return self.string_byte(b, key, len, escape),
+            Lexeme::Literal { word, pos } => {
+                if word.get(pos) != Some(&b) {
+                    return Err(self.violation(JsonViolation::Malformed, true));
+                }
+                self.lexeme = if pos + 1 == word.len() {
+                    self.end_value();
+                    Lexeme::None
+                } else {
+                    Lexeme::Literal { word, pos: pos + 1 }
+                };
+                return Ok(());
+            }
+            Lexeme::Number(number) => {
+                if let Some(number) = number.next(b) {
+                    self.lexeme = Lexeme::Number(number);
+                    return Ok(());
+                }
+                if !number.complete() {
+                    return Err(self.violation(JsonViolation::Malformed, true));
+                }
+                self.lexeme = Lexeme::None;
+                self.end_value();
+            }
+            Lexeme::None => {}
+        }
+
+        if matches!(b, b' ' | b'\t' | b'\n' | b'\r') {
+            return Ok(());
+        }
+        match (self.expect, b) {
+            (Expect::ValueOrClose, b']') | (Expect::KeyOrClose, b'}') => {
+                self.close();
+                Ok(())
+            }
+            (Expect::Value | Expect::ValueOrClose, _) => self.start_value(b),
+            (Expect::Key | Expect::KeyOrClose, b'"') => self.start_key(),
+            (Expect::Colon, b':') => {
+                self.expect = Expect::Value;
+                Ok(())
+            }
+            (Expect::CommaOrClose, b',') => {
+                self.expect = match self.stack.last() {
+                    Some(Frame::Array { .. }) => Expect::Value,
+                    _ => Expect::Key,
+                };
+                Ok(())
+            }
+            (Expect::CommaOrClose, b']')
+                if matches!(self.stack.last(), Some(Frame::Array { .. })) =>
+            {
+                self.close();
+                Ok(())
+            }
+            (Expect::CommaOrClose, b'}')
+                if matches!(self.stack.last(), Some(Frame::Object { .. })) =>
+            {
+                self.close();
+                Ok(())
+            }
+            _ => Err(self.violation(JsonViolation::Malformed, true)),
+        }
+    }
+
+    fn string_byte(&mut self, b: u8, key: bool, len: usize, escape: Escape) -> Result<()> {
+        let escape = match (escape, b) {
+            (Escape::None, b'"') => {
+                self.lexeme = Lexeme::None;
+                if key {
+                    self.expect = Expect::Colon;
+                } else {
+                    self.end_value();
+                }
+                return Ok(());
+            }
+            (Escape::None, b'\\') => Escape::Backslash,
+            (Escape::None, 0..=0x1f) => {
+                return Err(self.violation(JsonViolation::Malformed, !key));
+            }
+            (Escape::None, _) => {
+                self.push_key(key, &[b]);
+                Escape::None
+            }
+            (Escape::Backslash, b'u') => Escape::Unicode { digits: 0, code: 0 },
+            (Escape::Backslash, _) => {
+                let unescaped = match b {
+                    b'"' | b'\\' | b'/' => b,
+                    b'b' => 0x08,
+                    b'f' => 0x0c,
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    _ => return Err(self.violation(JsonViolation::Malformed, !key)),
+                };
+                self.push_key(key, &[unescaped]);
+                Escape::None
+            }
+            (Escape::Unicode { digits, code }, _) => {
+                let Some(digit) = char::from(b).to_digit(16) else {
+                    return Err(self.violation(JsonViolation::Malformed, !key));
+                };
+                let code = code << 4 | digit;
+                if digits == 3 {
+                    let c = char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+                    self.push_key(key, c.encode_utf8(&mut [0; 4]).as_bytes());
+                    Escape::None
+                } else {
+                    Escape::Unicode {
+                        digits: digits + 1,
+                        code,
+                    }
+                }
+            }
+        };
+
+        let len = len + 1;
+        if let Some(max_string_len) = self.limits.max_string_len {
+            if len > max_string_len {
+                return Err(self.violation(JsonViolation::StringTooLong { max_string_len }, !key));
+            }
+        }
+        self.lexeme = Lexeme::String { key, len, escape };
+
+        Ok(())
+    }
+
+    fn push_key(&mut self, key: bool, bytes: &[u8]) {
+        if let (true, Some(Frame::Object { key, .. })) = (key, self.stack.last_mut()) {
+            let len = bytes.len().min(MAX_KEY_LEN.saturating_sub(key.len()));
+            key.extend_from_slice(&bytes[..len]);
+        }
+    }
+
+    fn start_value(&mut self, b: u8) -> Result<()> {
+        if let Some(Frame::Array { len }) = self.stack.last_mut() {
+            *len += 1;
+            let len = *len;
+            self.check_len(len)?;
+        }
+        self.count_token()?;
+
+        match b {
+            b'{' | b'[' => {
+                if let Some(max_depth) = self.limits.max_depth {
+                    if self.stack.len() >= max_depth {
+                        return Err(self.violation(JsonViolation::TooDeep { max_depth }, true));
+                    }
+                }
+                if b == b'{' {
+                    self.stack.push(Frame::Object {
+                        len: 0,
+                        key: Vec::new(),
+                    });
+                    self.expect = Expect::KeyOrClose;
+                } else {
+                    self.stack.push(Frame::Array { len: 0 });
+                    self.expect = Expect::ValueOrClose;
+                }
+            }
+            b'"' => {
+                self.lexeme = Lexeme::String {
+                    key: false,
+                    len: 0,
+                    escape: Escape::None,
+                }
+            }
+            b'-' | b'0'..=b'9' => self.lexeme = Lexeme::Number(Number::start(b)),
+            b't' => {
+                self.lexeme = Lexeme::Literal {
+                    word: b"true",
+                    pos: 1,
+                }
+            }
+            b'f' => {
+                self.lexeme = Lexeme::Literal {
+                    word: b"false",
+                    pos: 1,
+                }
+            }
+            b'n' => {
+                self.lexeme = Lexeme::Literal {
+                    word: b"null",
+                    pos: 1,
+                }
+            }
+            _ => return Err(self.violation(JsonViolation::Malformed, true)),
+        }
+
+        Ok(())
+    }
+
+    fn start_key(&mut self) -> Result<()> {
+        if let Some(Frame::Object { len, key }) = self.stack.last_mut() {
+            *len += 1;
+            key.clear();
+            let len = *len;
+            self.check_len(len)?;
+        }
+        self.count_token()?;
+        self.lexeme = Lexeme::String {
+            key: true,
+            len: 0,
+            escape: Escape::None,
+        };
+
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.stack.pop();
+        self.end_value();
+    }
+
+    fn end_value(&mut self) {
+        self.expect = if self.stack.is_empty() {
+            Expect::End
+        } else {
+            Expect::CommaOrClose
+        };
+    }
+
+    fn check_len(&self, len: usize) -> Result<()> {
+        match self.limits.max_len {
+            Some(max_len) if len > max_len => {
+                Err(self.violation(JsonViolation::TooLong { max_len }, false))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn count_token(&mut self) -> Result<()> {
+        self.tokens += 1;
+        match self.limits.max_tokens {
+            Some(max_tokens) if self.tokens > max_tokens => {
+                Err(self.violation(JsonViolation::TooManyTokens { max_tokens }, true))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Locates a violation by the pointer to the current value, or to its array or object unless `in_value`.
+    fn violation(&self, violation: JsonViolation, in_value: bool) -> LimitReaderError {
+        let frames = if in_value {
+            &self.stack[..]
+        } else {
+            &self.stack[..self.stack.len().saturating_sub(1)]
+        };
+        let mut pointer = String::new();
+        for frame in frames {
+            pointer.push('/');
+            match frame {
+                Frame::Array { len } => pointer.push_str(&len.saturating_sub(1).to_string()),
+                Frame::Object { key, .. } => pointer.push_str(
+                    &String::from_utf8_lossy(key)
+                        .replace('~', "~0")
+                        .replace('/', "~1"),
+                ),
+            }
+        }
+
+        let kind = match violation {
+            JsonViolation::Malformed => ErrorKind::Deserialize,
+            _ => ErrorKind::JsonLimitExceeded,
+        };
+
+        LimitReaderError::new(
+            kind,
+            JsonLimitError {
+                pointer,
+                offset: self.offset,
+                violation,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Yields at most 3 bytes per read.
+    struct Chunked<'a>(&'a [u8]);
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+
+            Ok(n)
+        }
+    }
+
+    fn validate(json: &str, limits: &JsonLimits) -> Result<()> {
+        JsonReader::new(Chunked(json.as_bytes()), limits).validate()
+    }
+
+    fn violation(json: &str, limits: &JsonLimits) -> JsonLimitError {
+        let err = validate(json, limits).unwrap_err();
+        err.get_ref()
+            .downcast_ref::<JsonLimitError>()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn reports_pointer_to_violation() {
+        let json = r#"{"users": [{"name": "Mike was here"}, {"a/b": {"tags": [[["deep"]]]}}], "n": -1.5e3}"#;
+        validate(json, &JsonLimits::new()).unwrap();
+
+        let mut limits = JsonLimits::new();
+        limits.max_depth(5);
+        let err = violation(json, &limits);
+        assert_eq!(err.violation(), JsonViolation::TooDeep { max_depth: 5 });
+        assert_eq!(err.pointer(), "/users/1/a~1b/tags/0");
+
+        let mut limits = JsonLimits::new();
+        limits.max_string_len(5);
+        let err = violation(json, &limits);
+        assert_eq!(err.pointer(), "/users/0/name");
+        assert_eq!(
+            err.to_string(),
+            "`/users/0/name` at offset 26 exceeds the maximum string length of 5 bytes"
+        );
+
+        let mut limits = JsonLimits::new();
+        limits.max_len(1);
+        assert_eq!(violation(json, &limits).pointer(), "/users");
+
+        let mut limits = JsonLimits::new();
+        limits.max_tokens(8);
+        assert_eq!(
+            violation(json, &limits).violation(),
+            JsonViolation::TooManyTokens { max_tokens: 8 }
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        let limits = JsonLimits::new();
+        for json in [
+            "",
+            "[1, 2",
+            r#"{"a" 1}"#,
+            "[1,]",
+            "tru",
+            "[1] 2",
+            r#""\x""#,
+            "-",
+            "01",
+            "1-2",
+            "[1e]",
+            "--",
+            "[1.]",
+            "[.5]",
+            "+1",
+        ] {
+            let err = validate(json, &limits).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Deserialize, "{json}");
+        }
+        for json in [
+            "0",
+            " true ",
+            r#"[null, {}, [], "é\"", 1e-3]"#,
+            "-0.5E+10",
+            "[10, 0e0, -1]",
+        ] {
+            validate(json, &limits).unwrap();
+        }
+    }
+
+    #[test]
+    fn cuts_long_keys_short_in_pointer() {
+        let json = format!(r#"{{"{}": [[1]]}}"#, "k".repeat(100_000));
+        let mut limits = JsonLimits::new();
+        limits.max_depth(2);
+        let err = violation(&json, &limits);
+        assert_eq!(err.pointer(), format!("/{}/0", "k".repeat(MAX_KEY_LEN)));
+    }
+
+    #[test]
+    fn stops_nested_arrays_before_they_are_passed_on() {
+        let json = "[".repeat(100_000);
+        let mut limits = JsonLimits::new();
+        limits.max_depth(64);
+
+        let mut reader = JsonReader::new(json.as_bytes(), &limits);
+        let err = LimitReaderError::from(reader.read_to_end(&mut Vec::new()).unwrap_err());
+        assert_eq!(err.kind(), ErrorKind::JsonLimitExceeded);
+        let err = err.get_ref().downcast_ref::<JsonLimitError>().unwrap();
+        assert_eq!(err.offset(), 64);
+        assert_eq!(err.pointer().matches('/').count(), 64);
+    }
+}
@@ -7,22 +7,83 @@
 //! This crate is heavily inspired by Jon Gjengset's "Crust of Rust" episode on the inner workings of git on `YouTube` (<https://youtu.be/u0VotuGzD_w?si=oIuV9CITSWHJXKBu&t=3503>) and mitigrating Zip-bombs.
 
 use derive_builder::Builder;
+use digest::DigestConfig;
 use error::LimitReaderError;
-use flate2::read::ZlibDecoder;
-use readable::MyBufReader;
-use readable::Readable;
-use readable::{falible::LimitReaderFallible, infalible::LimitReaderInfallible};
+use observer::{CountingReader, Observation, SharedCountingReader};
+use quota::QuotaCharge;
+use rate::TokenBucket;
+use readable::{Adapter, Hooks};
+use std::cell::Cell;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::io;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::Duration;
+use tee::{Tee, TeeReader};
+use timeout::Timeouts;
 
 use LimitReaderResult as Result;
 
+pub(crate) mod budget;
+pub(crate) mod cancel;
+pub(crate) mod chunked;
+pub(crate) mod clock;
+#[cfg(any(feature = "json", feature = "bincode", feature = "cbor"))]
+pub(crate) mod deserialize;
+pub(crate) mod digest;
+pub(crate) mod encoding;
 pub(crate) mod error;
+pub(crate) mod frame;
+#[cfg(feature = "git")]
+pub mod git;
+pub(crate) mod inspect;
+pub(crate) mod json;
+pub(crate) mod lines;
+pub(crate) mod observer;
+pub(crate) mod quota;
+pub(crate) mod rate;
 pub(crate) mod readable;
+pub(crate) mod scan;
+pub(crate) mod stage;
+pub(crate) mod tee;
+pub(crate) mod text;
+pub(crate) mod timeout;
+#[cfg(feature = "xml")]
+pub(crate) mod xml;
+#[cfg(feature = "yaml")]
+pub(crate) mod yaml;
+
+pub use budget::{BudgetExceededError, SharedBudget};
+pub use cancel::{CancellationToken, CancelledError};
+pub use chunked::{ChunkedDecoder, ChunkedLimitError, ChunkedLimits, ChunkedViolation};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use digest::{Digest, DigestAlgorithm, DigestMismatchError};
+pub use encoding::Encoding;
+pub use error::{BoxError, ErrorKind, RatioExceededError, UnexpectedEofError};
+#[cfg(feature = "tokio")]
+pub use frame::FrameCodec;
+pub use frame::{Endian, FrameLimitError, FrameLimits, FrameReader, FrameViolation, LengthPrefix};
+pub use inspect::{Chunk, Inspector, RejectedError};
+pub use json::{JsonLimitError, JsonLimits, JsonReader, JsonViolation};
+pub use lines::{LineLimitError, LineLimits, LinePolicy, LineReader, LineViolation};
+pub use observer::{Observer, Progress};
+pub use quota::{Quota, QuotaSnapshot};
+#[cfg(feature = "tokio")]
+pub use rate::AsyncRateLimitReader;
+pub use readable::{falible::LimitReaderFallible, infalible::LimitReaderInfallible};
+pub use scan::{FileReport, ScanReport, Scanner, Verdict};
+pub use stage::Stage;
+pub use tee::TeeStats;
+pub use text::TextBoundary;
+pub use timeout::{TimeoutError, TimeoutKind};
+#[cfg(feature = "xml")]
+pub use xml::{XmlLimitError, XmlLimits, XmlReader, XmlViolation};
+#[cfg(feature = "yaml")]
+pub use yaml::{YamlLimitError, YamlLimits, YamlViolation};
 
 /// Default result type for [`LimitReader`]
 pub type LimitReaderResult<T> = std::result::Result<T, LimitReaderError>;
@@ -30,16 +91,38 @@ pub type LimitReaderResult<T> = std::result::Result<T, LimitReaderError>;
 /// Re-exports Traits and macros used by most projects. Add `use better_limit_reader::prelude::*;` to your code to quickly get started with [`LimitReader`].
 pub mod prelude {
 
-    pub use crate::{error::LimitReaderError, LimitReader, LimitReaderOutput, LimitReaderResult};
+    pub use crate::{
+        error::{ErrorKind, LimitReaderError, UnexpectedEofError},
+        Clock, DigestAlgorithm, LimitReader, LimitReaderFallible, LimitReaderInfallible,
+        LimitReaderOutput, LimitReaderResult, LineLimits, LinePolicy, LineReader, Stage, TeeStats,
+        TextBoundary,
+    };
 }
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::struct_excessive_bools)]
 /// The [`LimitReader`] reads into `buf` which is held within the record struct.
 pub struct LimitReader {
     buf: [u8; Self::DEFAULT_BUF_SIZE],
     expected_size: u64,
+    min_size: u64,
     decode_zlib: bool,
     decode_gzip: bool,
+    decode_auto: bool,
+    #[cfg(feature = "brotli")]
+    decode_brotli: bool,
+    chunked: Option<ChunkedLimits>,
+    max_ratio: Option<f64>,
+    digest_config: DigestConfig,
+    digests: Vec<Digest>,
+    tee: Option<(Stage, Tee)>,
+    timeouts: Timeouts,
+    clock: Option<Arc<dyn Clock>>,
+    rate: Option<TokenBucket>,
+    throttled: Duration,
+    cancel: Option<CancellationToken>,
+    observation: Option<Observation>,
+    budget: Option<SharedBudget>,
+    quota: Option<Quota>,
 }
 
 impl Default for LimitReader {
@@ -59,8 +142,25 @@ impl LimitReader {
         Self {
             buf: [0; Self::DEFAULT_BUF_SIZE],
             expected_size: (Self::DEFAULT_BUF_SIZE - 1) as u64,
+            min_size: 0,
             decode_zlib: false,
             decode_gzip: false,
+            decode_auto: false,
+            #[cfg(feature = "brotli")]
+            decode_brotli: false,
+            chunked: None,
+            max_ratio: None,
+            digest_config: DigestConfig::default(),
+            digests: Vec::new(),
+            tee: None,
+            timeouts: Timeouts::default(),
+            clock: None,
+            rate: None,
+            throttled: Duration::ZERO,
+            cancel: None,
+            observation: None,
+            budget: None,
+            quota: None,
         }
     }
 
@@ -77,6 +177,23 @@ impl LimitReader {
         self
     }
 
+    /// Require the source to hold exactly `size` bytes (after decoding), e.g. as declared by a `Content-Length` header.  This sets both the limit and the minimum size.
+    ///
+    /// Reading fewer bytes fails with [`ErrorKind::UnexpectedEof`], while reading more bytes fails with [`ErrorKind::LimitExceeded`] (or is truncated by [`LimitReader::read_limited`]).  The buffered reads hold at most [`LimitReader::DEFAULT_BUF_SIZE`] bytes, so they fail with [`ErrorKind::LimitExceeded`] for larger sizes; stream larger sources with [`LimitReader::copy`].
+    pub fn exact_size(&mut self, size: u64) -> &mut Self {
+        self.expected_size = size;
+        self.min_size = size;
+
+        self
+    }
+
+    /// Require the source to hold at least `size` bytes (after decoding).  Reading fewer bytes fails with [`ErrorKind::UnexpectedEof`], which the readers of structured formats such as [`LimitReader::read_json`] report once the source ends.  As with [`LimitReader::exact_size`], the buffered reads fail with [`ErrorKind::LimitExceeded`] for sizes beyond [`LimitReader::DEFAULT_BUF_SIZE`].
+    pub fn min_size(&mut self, size: u64) -> &mut Self {
+        self.min_size = size;
+
+        self
+    }
+
     /// Enable decoding from compressed Zlib
     pub fn enable_decode_zlib(&mut self) -> &mut Self {
         self.decode_zlib = true;
@@ -84,16 +201,159 @@ impl LimitReader {
         self
     }
 
-    #[allow(dead_code)]
-    // NOTE: This is private until this is implemented in the future.
     /// Enable decoding from compressed Gzip
-    fn enable_decode_gzip(&mut self) -> &mut Self {
+    pub fn enable_decode_gzip(&mut self) -> &mut Self {
         self.decode_gzip = true;
 
         self
     }
 
-    /// Read from provided source file.  If the source data is already Zlib compressed, optionally decode the data stream before reading it through a limit-reader.
+    /// Enable decoding from Zlib or Gzip, as detected from the first bytes of each source.  Sources in neither format are read as is.  The encoding detected is reported by [`LimitReaderOutput::encoding`].
+    pub fn enable_decode_auto(&mut self) -> &mut Self {
+        self.decode_auto = true;
+
+        self
+    }
+
+    /// Enable decoding from compressed Brotli.  Brotli cannot be detected by [`LimitReader::enable_decode_auto`].
+    #[cfg(feature = "brotli")]
+    pub fn enable_decode_brotli(&mut self) -> &mut Self {
+        self.decode_brotli = true;
+
+        self
+    }
+
+    /// Decode the source from the HTTP/1.1 chunked transfer coding, enforcing `limits`, before any content decoding such as Gzip.  Sources are then sized, and ratios computed, by their bytes as received.  Violations fail with [`ErrorKind::ChunkedLimitExceeded`]; see [`ChunkedDecoder`].
+    pub fn decode_chunked(&mut self, limits: &ChunkedLimits) -> &mut Self {
+        self.chunked = Some(limits.clone());
+
+        self
+    }
+
+    /// Fail with [`ErrorKind::RatioExceeded`] once the decoded bytes exceed `ratio` times the compressed bytes consumed, catching decompression bombs long before they reach the limit.  This applies to the truncating reads as well, and to the readers of structured formats such as [`LimitReader::read_json`].
+    pub fn max_ratio(&mut self, ratio: f64) -> &mut Self {
+        self.max_ratio = Some(ratio);
+
+        self
+    }
+
+    /// Compute a digest of the `stage` bytes while reading, avoiding a second pass over the source.  Digests are returned by [`LimitReaderOutput::digests`] and [`LimitReader::digests`].
+    ///
+    /// A [`Stage::Raw`] digest covers the whole source: once a read completes, the rest of the source is consumed, without decoding, to finish the digest.  The rest is read under the same time limits and cancellation as the read, up to the size of a file, or up to as many bytes as the limit in total for sources of unknown size.  A source holding more than that yields a [`Digest::partial`] digest.
+    pub fn digest(&mut self, algorithm: DigestAlgorithm, stage: Stage) -> &mut Self {
+        self.digest_config.add(algorithm, stage);
+
+        self
+    }
+
+    /// Compute a digest as per [`LimitReader::digest`], failing the read with [`ErrorKind::DigestMismatch`] unless it equals the `expected` hex value.
+    pub fn expect_digest(
+        &mut self,
+        algorithm: DigestAlgorithm,
+        stage: Stage,
+        expected: &str,
+    ) -> &mut Self {
+        self.digest_config.expect(algorithm, stage, expected);
+
+        self
+    }
+
+    /// Digests computed by the last read.
+    #[must_use]
+    pub fn digests(&self) -> &[Digest] {
+        &self.digests
+    }
+
+    /// Mirror the `stage` bytes of each read into `writer`, e.g. for audit logging.  At most `cap` bytes are mirrored per read; further bytes are dropped without affecting the read, as are errors of `writer`, which are reported by [`TeeStats::error`].
+    ///
+    /// Decoders read ahead, so raw bytes may be mirrored beyond those needed to reach the limit.
+    pub fn tee(
+        &mut self,
+        writer: impl Write + Send + 'static,
+        stage: Stage,
+        cap: u64,
+    ) -> &mut Self {
+        self.tee = Some((stage, Tee::new(writer, cap)));
+
+        self
+    }
+
+    /// Bytes mirrored by [`LimitReader::tee`] during the last read, if set.
+    #[must_use]
+    pub fn tee_stats(&self) -> Option<TeeStats> {
+        self.tee.as_ref().map(|(_, tee)| tee.stats())
+    }
+
+    /// Fail a read with [`ErrorKind::Timeout`] once it takes longer than `deadline`, including time spent decoding.
+    ///
+    /// A blocking read of the source cannot be interrupted, so this is checked whenever it returns.
+    pub fn deadline(&mut self, deadline: Duration) -> &mut Self {
+        self.timeouts.deadline(deadline);
+
+        self
+    }
+
+    /// Fail a read with [`ErrorKind::Timeout`] once the decoded source yields no bytes for longer than `idle_timeout`, e.g. when it trickles in.
+    pub fn idle_timeout(&mut self, idle_timeout: Duration) -> &mut Self {
+        self.timeouts.idle_timeout(idle_timeout);
+
+        self
+    }
+
+    /// Limit the throughput of the decoded source to `bytes_per_second` on average, and to `burst` bytes at once, sleeping while throttled.
+    pub fn rate_limit(&mut self, bytes_per_second: u64, burst: u64) -> &mut Self {
+        self.rate = Some(TokenBucket::new(bytes_per_second, burst));
+
+        self
+    }
+
+    /// Time spent throttled by [`LimitReader::rate_limit`] during the last read.
+    #[must_use]
+    pub fn throttled(&self) -> Duration {
+        self.throttled
+    }
+
+    /// Measure time limits, and sleep while throttled, with `clock` rather than the [`SystemClock`].
+    pub fn clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = Some(clock);
+
+        self
+    }
+
+    /// Abort reads with [`ErrorKind::Cancelled`] once `token` is cancelled.  The counts of the partial read are carried by a [`CancelledError`].
+    pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel = Some(token);
+
+        self
+    }
+
+    /// Draw every decoded byte from `budget` as well as the limit, e.g. to enforce a total across the files of a request.  Reads crossing it fail with [`ErrorKind::BudgetExceeded`].
+    pub fn shared_budget(&mut self, budget: SharedBudget) -> &mut Self {
+        self.budget = Some(budget);
+
+        self
+    }
+
+    /// Charge every decoded byte to `quota` and its ancestors, e.g. a request, its tenant and the process-wide pool.  Once any of them is exhausted, [`LimitReader::read`] fails with [`ErrorKind::BudgetExceeded`], while [`LimitReader::read_limited`] truncates.
+    pub fn quota(&mut self, quota: Quota) -> &mut Self {
+        self.quota = Some(quota);
+
+        self
+    }
+
+    /// Report the progress of reads to `observer`, at most once every `every_bytes` decoded bytes or `every` interval, and once more when a read completes.  The observer may abort a read by returning an error.
+    pub fn observe(
+        &mut self,
+        observer: impl Observer + 'static,
+        every_bytes: u64,
+        every: Duration,
+    ) -> &mut Self {
+        self.observation = Some(Observation::new(observer, every_bytes, every));
+
+        self
+    }
+
+    /// Read from provided source file.  If the source data is already Zlib or Gzip compressed, optionally decode the data stream before reading it through a limit-reader.
     ///
     /// # Panics
     ///
@@ -101,22 +361,14 @@ impl LimitReader {
     ///
     /// # Errors
     ///
-    /// If this function encounters an error of the kind [`LimitReaderError`], this error will be returned.
+    /// If this function encounters an error of the kind [`LimitReaderError`], this error will be returned.  A source exceeding the limit, or holding more bytes than fit in the buffer, fails with [`ErrorKind::LimitExceeded`].
     ///
     pub fn read(&mut self, source: PathBuf) -> Result<usize> {
         let f = std::fs::File::open(source).expect("Unable to open file");
-        if self.decode_zlib {
-            let z = ZlibDecoder::new(f);
-            let buf_reader = MyBufReader(z);
-            let reader = LimitReaderFallible::new(buf_reader, self.expected_size);
+        let source_size = f.metadata()?.len();
+        let outcome = self.read_source(f, Some(source_size), true, Sink::Buffer)?;
 
-            self.try_read(reader)
-        } else {
-            let buf_reader = MyBufReader(BufReader::new(f));
-            let reader = LimitReaderFallible::new(buf_reader, self.expected_size);
-
-            self.try_read(reader)
-        }
+        Ok(outcome.bytes_read)
     }
 
     /// Given an accessible source file, this will automatically limit the contents read to the size of the buffer itself.  This will silently truncate read bytes into the buffer, without raising an error.
@@ -126,63 +378,621 @@ impl LimitReader {
     /// If this function encounters an error of the kind [`LimitReaderError`], this error will be returned.
     ///
     pub fn read_limited(&mut self, source: PathBuf) -> Result<LimitReaderOutput> {
+        let (_, output) = self.read_limited_aligned(source, None)?;
+
+        Ok(output)
+    }
+
+    /// Like [`LimitReader::read_limited`], but returns the bytes read as a `String`.  If the limit truncated the source, the text is cut at the last complete `boundary` rather than at an arbitrary byte offset; the number of trailing bytes dropped for this is reported by [`LimitReaderOutput::boundary_bytes_dropped`].
+    ///
+    /// # Errors
+    ///
+    /// If the aligned text is not valid UTF-8, this returns a [`LimitReaderError`] of the kind [`ErrorKind::Utf8Error`].  Refer to [`LimitReader::read_limited_text_lossy`] to substitute invalid bytes instead.
+    ///
+    pub fn read_limited_text(
+        &mut self,
+        source: PathBuf,
+        boundary: TextBoundary,
+    ) -> Result<(String, LimitReaderOutput)> {
+        let (len, output) = self.read_limited_aligned(source, Some(boundary))?;
+        let text = String::from_utf8(self.buf[..len].to_vec())?;
+
+        Ok((text, output))
+    }
+
+    /// Like [`LimitReader::read_limited_text`], but substitutes invalid UTF-8 sequences with `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// # Errors
+    ///
+    /// If this function encounters an error of the kind [`LimitReaderError`], this error will be returned.
+    ///
+    pub fn read_limited_text_lossy(
+        &mut self,
+        source: PathBuf,
+        boundary: TextBoundary,
+    ) -> Result<(String, LimitReaderOutput)> {
+        let (len, output) = self.read_limited_aligned(source, Some(boundary))?;
+        let text = String::from_utf8_lossy(&self.buf[..len]).into_owned();
+
+        Ok((text, output))
+    }
+
+    // Reads through a `LimitReaderInfallible`, returning the length of the buffered prefix that ends on `boundary`.
+    fn read_limited_aligned(
+        &mut self,
+        source: PathBuf,
+        boundary: Option<TextBoundary>,
+    ) -> Result<(usize, LimitReaderOutput)> {
         let source_bytes = std::fs::metadata(&source)?.len();
         let f = std::fs::File::open(source)?;
 
-        let bytes_read = if self.decode_zlib {
-            let z = ZlibDecoder::new(f);
-            let buf_reader = MyBufReader(z);
-            let reader = LimitReaderInfallible::new(buf_reader, self.expected_size);
+        let outcome = self.read_source(f, Some(source_bytes), false, Sink::Buffer)?;
+        let bytes_read = outcome.bytes_read;
+        let len = match boundary {
+            Some(boundary) => {
+                text::aligned_len(&self.buf[..bytes_read], boundary, outcome.truncated)
+            }
+            None => bytes_read,
+        };
+
+        let output = self
+            .output(outcome)
+            .boundary_bytes_dropped((bytes_read - len) as u64)
+            .build()?;
+
+        Ok((len, output))
+    }
+
+    /// Stream `source` through the configured decoder into `out`, e.g. from stdin to stdout.  Unlike [`LimitReader::read`], the bytes are not held in the buffer, so the limit is not bound by [`LimitReader::DEFAULT_BUF_SIZE`].  As the size of a stream is unknown up front, [`LimitReaderOutput::source_size`] reports the bytes consumed from `source`.
+    ///
+    /// # Errors
+    ///
+    /// Once the source exceeds the limit, this returns a [`LimitReaderError`] of the kind [`ErrorKind::LimitExceeded`]; the bytes accepted before that have already been written to `out`.  If writing to `out` fails, this returns a [`LimitReaderError`] of the kind [`ErrorKind::IoError`].
+    ///
+    pub fn copy(&mut self, source: impl Read, out: &mut impl Write) -> Result<LimitReaderOutput> {
+        let outcome = self.read_source(source, None, true, Sink::Writer(out))?;
+
+        Ok(self.output(outcome).build()?)
+    }
+
+    /// Like [`LimitReader::copy`], but silently stops at the limit rather than returning an error.
+    ///
+    /// # Errors
+    ///
+    /// If writing to `out` fails, this returns a [`LimitReaderError`] of the kind [`ErrorKind::IoError`].
+    ///
+    pub fn copy_limited(
+        &mut self,
+        source: impl Read,
+        out: &mut impl Write,
+    ) -> Result<LimitReaderOutput> {
+        let outcome = self.read_source(source, None, false, Sink::Writer(out))?;
+
+        Ok(self.output(outcome).build()?)
+    }
+
+    // Starts the output of a read from its outcome.
+    fn output(&self, outcome: ReadOutcome) -> LimitReaderOutputBuilder {
+        let mut builder = LimitReaderOutputBuilder::default();
+        builder
+            .source_size(outcome.source_size.unwrap_or(outcome.compressed_bytes))
+            .compressed_bytes(outcome.compressed_bytes)
+            .compressed_remaining(outcome.source_size.and_then(|size| {
+                // Decoders read ahead, so the bytes they needed are only known once they are done.
+                match outcome.encoding {
+                    Encoding::Identity => size.checked_sub(outcome.bytes_read as u64),
+                    _ if outcome.truncated => None,
+                    _ => size.checked_sub(outcome.compressed_bytes),
+                }
+            }))
+            .bytes_remaining(
+                outcome
+                    .source_size
+                    .filter(|_| outcome.encoding == Encoding::Identity)
+                    .and_then(|size| size.checked_sub(outcome.bytes_read as u64)),
+            )
+            .bytes_read(outcome.bytes_read as u64)
+            .reader_count(outcome.reader_count as u64)
+            .truncated(outcome.truncated)
+            .digests(outcome.digests)
+            .tee_stats(self.tee_stats())
+            .throttled(self.throttled)
+            .elapsed(outcome.elapsed)
+            .encoding(outcome.encoding);
+
+        builder
+    }
+
+    /// Read lines from provided source file, decoding it as configured.  Returns an error once the source exceeds the limit, in addition to enforcing `limits` on each line.  The other policies configured, such as [`LimitReader::max_ratio`], [`LimitReader::min_size`], time limits and cancellation, apply as the lines are read.
+    ///
+    /// # Errors
+    ///
+    /// If the source cannot be opened, this returns a [`LimitReaderError`] of the kind [`ErrorKind::IoError`].
+    ///
+    pub fn read_lines(
+        &self,
+        source: PathBuf,
+        limits: &LineLimits,
+    ) -> Result<LineReader<LimitReaderFallible<Box<dyn Read>>>> {
+        let f = std::fs::File::open(source)?;
+
+        Ok(LineReader::new(self.strict_reader(f)?, limits))
+    }
+
+    /// Read lines from provided source file, decoding it as configured.  Silently stops at the limit, in addition to enforcing `limits` on each line.  The other policies configured apply as per [`LimitReader::read_lines`], with quotas truncating rather than failing.
+    ///
+    /// # Errors
+    ///
+    /// If the source cannot be opened, this returns a [`LimitReaderError`] of the kind [`ErrorKind::IoError`].
+    ///
+    pub fn read_lines_limited(
+        &self,
+        source: PathBuf,
+        limits: &LineLimits,
+    ) -> Result<LineReader<LimitReaderInfallible<Box<dyn Read>>>> {
+        let f = std::fs::File::open(source)?;
+        let (decoder, hooks) = self.streaming(f, false)?;
+        let mut reader = LimitReaderInfallible::new(decoder, self.expected_size);
+        reader.set_hooks(hooks);
+
+        Ok(LineReader::new(reader, limits))
+    }
+
+    /// Read a JSON document from provided source file, decoding it as configured.  Returns an error once the source exceeds the limit or [`LimitReader::max_ratio`], or ends short of [`LimitReader::min_size`], in addition to enforcing `limits` on the structure of the document.
+    ///
+    /// # Errors
+    ///
+    /// If the source cannot be opened, this returns a [`LimitReaderError`] of the kind [`ErrorKind::IoError`].
+    ///
+    pub fn read_json(
+        &self,
+        source: PathBuf,
+        limits: &JsonLimits,
+    ) -> Result<JsonReader<LimitReaderFallible<Box<dyn Read>>>> {
+        let f = std::fs::File::open(source)?;
+
+        Ok(JsonReader::new(self.strict_reader(f)?, limits))
+    }
 
-            self.try_read(reader)?
+    // Wraps the source in the configured decoder, if any, detecting the encoding from the first bytes if enabled.
+    pub(crate) fn decoder<'a>(
+        &self,
+        r: impl Read + 'a,
+    ) -> io::Result<(Box<dyn Read + 'a>, Encoding)> {
+        let mut r: Box<dyn Read + 'a> = match &self.chunked {
+            Some(limits) => Box::new(ChunkedDecoder::new(BufReader::new(r), limits)),
+            None => Box::new(r),
+        };
+        #[cfg(feature = "brotli")]
+        if self.decode_brotli {
+            return Ok((
+                Encoding::Brotli.decoder(BufReader::new(r)),
+                Encoding::Brotli,
+            ));
+        }
+        let mut prefix = Vec::new();
+        let encoding = if self.decode_zlib {
+            Encoding::Zlib
+        } else if self.decode_gzip {
+            Encoding::Gzip
+        } else if self.decode_auto {
+            // Sources such as pipes may return fewer bytes than the magic bytes span.
+            (&mut r).take(2).read_to_end(&mut prefix)?;
+            Encoding::detect(&prefix)
         } else {
-            let buf_reader = MyBufReader(BufReader::new(f));
-            let reader = LimitReaderInfallible::new(buf_reader, self.expected_size);
+            Encoding::Identity
+        };
+        let r = BufReader::new(io::Cursor::new(prefix).chain(r));
 
-            self.try_read(reader)?
+        Ok((encoding.decoder(r), encoding))
+    }
+
+    // Reads the source through the configured decoder and a limit adapter (a `LimitReaderFallible` if `strict`) into the sink, enforcing the configured size and digest policies.
+    fn read_source(
+        &mut self,
+        source: impl Read,
+        source_size: Option<u64>,
+        strict: bool,
+        sink: Sink<'_>,
+    ) -> Result<ReadOutcome> {
+        if matches!(sink, Sink::Buffer) && self.min_size > self.buf.len() as u64 {
+            return Err(LimitReaderError::new(
+                ErrorKind::LimitExceeded,
+                format!(
+                    "minimum size of {} bytes exceeds the buffer of {} bytes",
+                    self.min_size,
+                    self.buf.len()
+                ),
+            ));
+        }
+        let mut tee = self.tee.take();
+        let (raw_tee, decoded_tee) = match &mut tee {
+            Some((stage, tee)) => {
+                tee.reset();
+                match stage {
+                    Stage::Raw => (Some(tee), None),
+                    Stage::Decoded => (None, Some(tee)),
+                }
+            }
+            None => (None, None),
         };
+        let outcome = self.fill(source, source_size, strict, sink, raw_tee, decoded_tee);
+        self.tee = tee;
+        let outcome = outcome?;
+
+        check_min_size(self.min_size, outcome.bytes_read as u64)?;
+        self.digest_config.verify(&outcome.digests)?;
 
-        Ok(LimitReaderOutputBuilder::default()
-            .source_size(source_bytes)
-            .bytes_read(bytes_read as u64)
-            .build()?)
+        Ok(outcome)
     }
 
-    fn try_read(&mut self, mut reader: impl Readable) -> Result<usize> {
-        let try_read = reader.perform_read(&mut self.buf);
-        match try_read {
-            Ok(value) => Ok(value),
-            Err(err) => Err(LimitReaderError::new(error::ErrorKind::ReadError, err)),
+    // Fills the sink from the source, computing digests and mirroring bytes into the tee on the way.  A source of unknown size is sized by the bytes consumed from it.
+    fn fill(
+        &mut self,
+        source: impl Read,
+        source_size: Option<u64>,
+        strict: bool,
+        sink: Sink<'_>,
+        raw_tee: Option<&mut Tee>,
+        decoded_tee: Option<&mut Tee>,
+    ) -> Result<ReadOutcome> {
+        let started = self.clock.as_deref().unwrap_or(&SystemClock).now();
+        if let Some(observation) = &mut self.observation {
+            observation.start();
         }
+        let compressed = Cell::new(0);
+        let mut raw = self.digest_config.reader(
+            TeeReader::new(CountingReader::new(source, &compressed), raw_tee),
+            Stage::Raw,
+        );
+        let hooks = self.hooks(strict);
+        let (decoder, encoding) = self
+            .decoder(&mut raw)
+            .map_err(|err| LimitReaderError::from_io(err, ErrorKind::ReadError))?;
+        let mut adapter = Adapter::new(decoder, self.expected_size, strict, hooks);
+
+        let mut reader = self
+            .digest_config
+            .reader(TeeReader::new(&mut adapter, decoded_tee), Stage::Decoded);
+        let mut bytes_read = 0;
+        let on_read = |decoded| {
+            if let Some(max_ratio) = self.max_ratio {
+                check_ratio(max_ratio, compressed.get(), decoded)?;
+            }
+            match &mut self.observation {
+                Some(observation) => observation.update(
+                    self.clock.as_deref().unwrap_or(&SystemClock).now(),
+                    compressed.get(),
+                    decoded,
+                    self.expected_size.saturating_sub(decoded),
+                ),
+                None => Ok(()),
+            }
+        };
+        let buffered = matches!(sink, Sink::Buffer);
+        let filled = match sink {
+            Sink::Buffer => fill_buffer(&mut self.buf, &mut reader, &mut bytes_read, on_read),
+            Sink::Writer(out) => copy_to(out, &mut reader, &mut bytes_read, on_read),
+        };
+        let decoded_digests = reader.finish(false);
+        self.throttled = adapter.hooks().throttled();
+        let outcome = |reader_count, truncated, digests| ReadOutcome {
+            source_size,
+            compressed_bytes: compressed.get(),
+            bytes_read,
+            reader_count,
+            truncated,
+            digests,
+            encoding,
+            elapsed: self
+                .clock
+                .as_deref()
+                .unwrap_or(&SystemClock)
+                .now()
+                .saturating_duration_since(started),
+        };
+        match filled {
+            Err(err) if err.kind() == ErrorKind::Cancelled => {
+                return Err(self.cancelled(outcome(adapter.reader_count(), false, Vec::new())));
+            }
+            result => result?,
+        }
+        let truncated = adapter.truncated(buffered && bytes_read == self.buf.len())?;
+        let reader_count = adapter.reader_count();
+        let mut hooks = adapter.into_hooks();
+        // Sources of unknown size are drained up to as many raw bytes as the limit in total.
+        let complete = if raw.has_digests() {
+            let rest = source_size
+                .unwrap_or(self.expected_size)
+                .saturating_sub(compressed.get());
+            match hooks
+                .drain(&mut raw, rest)
+                .map_err(|err| LimitReaderError::from_io(err, ErrorKind::ReadError))
+            {
+                Err(err) if err.kind() == ErrorKind::Cancelled => {
+                    return Err(self.cancelled(outcome(reader_count, truncated, Vec::new())));
+                }
+                result => result?,
+            }
+        } else {
+            true
+        };
+        if let Some(observation) = &mut self.observation {
+            observation.finish(
+                compressed.get(),
+                bytes_read as u64,
+                self.expected_size.saturating_sub(bytes_read as u64),
+            )?;
+        }
+
+        let mut digests = raw.finish(!complete);
+        digests.extend(decoded_digests);
+        self.digests.clone_from(&digests);
+
+        Ok(outcome(reader_count, truncated, digests))
     }
+
+    // The hooks of a limit adapter enforcing the configured policies, truncating rather than failing unless `strict`.
+    fn hooks(&self, strict: bool) -> Hooks {
+        Hooks {
+            clock: self.clock.clone(),
+            timeouts: self.timeouts.clone(),
+            rate: self.rate.clone(),
+            cancel: self.cancel.clone(),
+            budget: self.budget.clone(),
+            quota: self
+                .quota
+                .clone()
+                .map(|quota| QuotaCharge::new(quota, !strict)),
+            ..Hooks::default()
+        }
+    }
+
+    // Wraps the source in the configured decoder and a `LimitReaderFallible` enforcing the configured policies, for callers that consume the stream themselves.
+    pub(crate) fn strict_reader<'a>(
+        &self,
+        source: impl Read + 'a,
+    ) -> Result<LimitReaderFallible<Box<dyn Read + 'a>>> {
+        let (decoder, hooks) = self.streaming(source, true)?;
+        let mut reader = LimitReaderFallible::new(decoder, self.expected_size);
+        reader.set_hooks(hooks);
+
+        Ok(reader)
+    }
+
+    // Wraps the source in the configured decoder, with the hooks of a limit adapter enforcing the configured policies on a stream consumed by the caller.  The ratio and minimum size are checked as the stream is read, as there is no end of the read to check them at.
+    fn streaming<'a>(
+        &self,
+        source: impl Read + 'a,
+        strict: bool,
+    ) -> Result<(Box<dyn Read + 'a>, Hooks)> {
+        let compressed = Arc::new(AtomicU64::new(0));
+        let (decoder, _) = self
+            .decoder(SharedCountingReader::new(source, Arc::clone(&compressed)))
+            .map_err(|err| LimitReaderError::from_io(err, ErrorKind::ReadError))?;
+        let hooks = Hooks {
+            ratio: self.max_ratio.map(|max_ratio| (max_ratio, compressed)),
+            min_size: self.min_size,
+            ..self.hooks(strict)
+        };
+
+        Ok((decoder, hooks))
+    }
+
+    // Attaches the counts of a partial read to a cancellation.
+    fn cancelled(&self, partial: ReadOutcome) -> LimitReaderError {
+        match self.output(partial).build() {
+            Ok(output) => LimitReaderError::new(ErrorKind::Cancelled, CancelledError { output }),
+            Err(err) => err.into(),
+        }
+    }
+}
+
+// Where `LimitReader::read_source` puts the decoded bytes.
+enum Sink<'a> {
+    Buffer,
+    Writer(&'a mut dyn Write),
+}
+
+// What was read by `LimitReader::read_source`.
+struct ReadOutcome {
+    source_size: Option<u64>,
+    compressed_bytes: u64,
+    bytes_read: usize,
+    reader_count: usize,
+    truncated: bool,
+    digests: Vec<Digest>,
+    encoding: Encoding,
+    elapsed: Duration,
+}
+
+// Reads until the end of the limited stream, or until `buf` is full, calling `on_read` with the total after each read.  `bytes_read` counts progress, including that made before an error.
+fn fill_buffer(
+    buf: &mut [u8],
+    reader: &mut impl Read,
+    bytes_read: &mut usize,
+    mut on_read: impl FnMut(u64) -> Result<()>,
+) -> Result<()> {
+    while *bytes_read < buf.len() {
+        match reader.read(&mut buf[*bytes_read..]) {
+            Ok(0) => break,
+            Ok(value) => {
+                *bytes_read += value;
+                on_read(*bytes_read as u64)?;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(LimitReaderError::from_io(err, ErrorKind::ReadError)),
+        }
+    }
+
+    Ok(())
+}
+
+// Like `fill_buffer`, but writes each chunk read to `out` rather than keeping it.
+fn copy_to(
+    out: &mut dyn Write,
+    reader: &mut impl Read,
+    bytes_read: &mut usize,
+    mut on_read: impl FnMut(u64) -> Result<()>,
+) -> Result<()> {
+    let mut buf = [0; 8 * 1024];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(value) => {
+                out.write_all(&buf[..value])?;
+                *bytes_read += value;
+                on_read(*bytes_read as u64)?;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(LimitReaderError::from_io(err, ErrorKind::ReadError)),
+        }
+    }
+
+    Ok(())
+}
+
+// Fails if fewer than `min_size` bytes were read.
+fn check_min_size(min_size: u64, bytes_read: u64) -> Result<()> {
+    if bytes_read < min_size {
+        return Err(LimitReaderError::new(
+            ErrorKind::UnexpectedEof,
+            UnexpectedEofError {
+                expected: min_size,
+                actual: bytes_read,
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+// Fails once `decoded_bytes` exceed `max_ratio` times the `compressed_bytes` they were decoded from.
+#[allow(clippy::cast_precision_loss)]
+fn check_ratio(max_ratio: f64, compressed_bytes: u64, decoded_bytes: u64) -> Result<()> {
+    if decoded_bytes as f64 > compressed_bytes.max(1) as f64 * max_ratio {
+        return Err(LimitReaderError::new(
+            ErrorKind::RatioExceeded,
+            RatioExceededError {
+                max_ratio,
+                compressed_bytes,
+                decoded_bytes,
+            },
+        ));
+    }
+
+    Ok(())
 }
 
 /// [`LimitReader`]'s output
 #[allow(missing_docs)]
-#[derive(Default, Builder)]
+#[derive(Debug, Clone, Default, Builder)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[builder(setter(into))]
 pub struct LimitReaderOutput {
     source_size: u64,
+    #[builder(default)]
+    compressed_bytes: u64,
+    #[builder(default)]
+    compressed_remaining: Option<u64>,
     bytes_read: u64,
+    #[builder(default)]
+    bytes_remaining: Option<u64>,
+    #[builder(default)]
+    reader_count: u64,
+    #[builder(default)]
+    truncated: bool,
+    #[builder(default)]
+    boundary_bytes_dropped: u64,
+    #[builder(default)]
+    digests: Vec<Digest>,
+    #[builder(default)]
+    tee_stats: Option<TeeStats>,
+    #[builder(default)]
+    throttled: Duration,
+    #[builder(default)]
+    elapsed: Duration,
+    #[builder(default)]
+    encoding: Encoding,
 }
 
 impl LimitReaderOutput {
-    /// Return bytes read by the underlying reader.
+    /// Return bytes read by the underlying reader, after decoding.  Compare with [`LimitReaderOutput::compressed_bytes`] for the bytes consumed from the source.
     #[must_use]
     pub fn bytes_read(&self) -> u64 {
         self.bytes_read
     }
 
-    /// Size in bytes of the underlying file accessible to the reader.
+    /// Size in bytes of the underlying file accessible to the reader.  For streams, whose size is not known up front, this is the number of bytes consumed; see [`LimitReader::copy`].
     #[must_use]
     pub fn source_size(&self) -> u64 {
         self.source_size
     }
 
-    /// Unread bytes (from the underlying file accessible to the reader).
+    /// Bytes consumed from the source, before decoding.  Decoders read ahead, so this may exceed the bytes needed to decode [`LimitReaderOutput::bytes_read`].
+    #[must_use]
+    pub fn compressed_bytes(&self) -> u64 {
+        self.compressed_bytes
+    }
+
+    /// Number of reads issued to the decoded source by the limit adapter.
+    #[must_use]
+    pub fn reader_count(&self) -> u64 {
+        self.reader_count
+    }
+
+    /// Whether the source held more bytes than were read, i.e. the truncating reads stopped at the limit or at the end of the buffer.
+    #[must_use]
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Trailing bytes read but dropped to align text on a [`TextBoundary`]; always zero outside of the text reading modes.
+    #[must_use]
+    pub fn boundary_bytes_dropped(&self) -> u64 {
+        self.boundary_bytes_dropped
+    }
+
+    /// Digests computed while reading; see [`LimitReader::digest`].
+    #[must_use]
+    pub fn digests(&self) -> &[Digest] {
+        &self.digests
+    }
+
+    /// Bytes mirrored by [`LimitReader::tee`], if set.
+    #[must_use]
+    pub fn tee_stats(&self) -> Option<TeeStats> {
+        self.tee_stats.clone()
+    }
+
+    /// Time spent throttled by [`LimitReader::rate_limit`].
+    #[must_use]
+    pub fn throttled(&self) -> Duration {
+        self.throttled
+    }
+
+    /// Time taken by the read, as measured by the [`Clock`].
     #[must_use]
-    pub fn bytes_remaining(&self) -> u64 {
-        self.source_size - self.bytes_read
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Encoding the source was decoded from; see [`LimitReader::enable_decode_auto`].
+    #[must_use]
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Unread bytes of the underlying file accessible to the reader.  This is only known for files read without decoding, as the decoded size of the rest of a compressed file is unknown; it is `None` for streams and decoded sources.
+    #[must_use]
+    pub fn bytes_remaining(&self) -> Option<u64> {
+        self.bytes_remaining
+    }
+
+    /// Bytes of the underlying file not consumed by the reader, before decoding.  Decoders read ahead of the bytes they need, so for decoded sources this is only known once the whole source was decoded; it is `None` for truncated decoded reads and for streams, whose size is not known.
+    #[must_use]
+    pub fn compressed_remaining(&self) -> Option<u64> {
+        self.compressed_remaining
     }
 }
 
@@ -197,12 +1007,16 @@ impl Display for LimitReaderOutput {
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_format)]
 mod tests {
-    use crate::LimitReader;
+    use crate::{
+        CancellationToken, ErrorKind, LimitReader, LimitReaderFallible, LimitReaderInfallible,
+        LineLimits, RatioExceededError, UnexpectedEofError,
+    };
     use flate2::write::ZlibEncoder;
     use flate2::Compression;
     use std::fs::File;
-    use std::io::Write;
+    use std::io::{BufRead, Write};
     use tempfile::tempdir;
 
     mod falible {
@@ -266,7 +1080,7 @@ mod tests {
 
             match limit_reader.read(file_path) {
                 Ok(read_size) => {
-                    assert!(read_size == limit.try_into().unwrap());
+                    assert!(read_size == usize::try_from(limit).unwrap());
                 }
                 Err(err) => {
                     assert_eq!("Error: too many bytes", err.to_string());
@@ -328,6 +1142,224 @@ mod tests {
             drop(file);
             dir.close().unwrap();
         }
+
+        #[test]
+        fn buf_read_lines_within_limit() {
+            let text = "Mike was here.\nBriefly.\n";
+            let reader = LimitReaderFallible::new(text.as_bytes(), text.len() as u64);
+
+            let lines: Vec<String> = reader.lines().map(|line| line.unwrap()).collect();
+            assert_eq!(lines, vec!["Mike was here.", "Briefly."]);
+        }
+
+        #[test]
+        fn buf_read_line_errors_past_limit() {
+            let text = "Mike was here.\nBriefly.\n";
+            let mut reader = LimitReaderFallible::new(text.as_bytes(), 8);
+
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(_) => unreachable!(),
+                Err(err) => assert_eq!("too many bytes", err.to_string()),
+            }
+        }
+
+        #[test]
+        fn exact_size_rejects_short_and_long_sources() {
+            let dir = tempdir().unwrap();
+
+            let text = "Mike was here. Briefly.";
+            let file_path = dir.path().join("test_output.txt");
+            let mut file = File::create(&file_path).unwrap();
+            write!(file, "{}", &text).unwrap();
+
+            let mut limit_reader = LimitReader::new();
+            limit_reader.exact_size(text.len() as u64);
+            assert_eq!(limit_reader.read(file_path.clone()).unwrap(), text.len());
+
+            limit_reader.exact_size(text.len() as u64 + 2);
+            let err = limit_reader.read(file_path.clone()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+            let eof = err.get_ref().downcast_ref::<UnexpectedEofError>().unwrap();
+            assert_eq!((eof.expected(), eof.actual()), (25, 23));
+
+            limit_reader.exact_size(8);
+            let err = limit_reader.read(file_path.clone()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+            assert_eq!("Error: too many bytes", err.to_string());
+
+            drop(file);
+            dir.close().unwrap();
+        }
+
+        #[test]
+        fn line_readers_enforce_cancellation_and_ratio() {
+            let dir = tempdir().unwrap();
+
+            let file_path = dir.path().join("test_output.txt");
+            let mut file = File::create(&file_path).unwrap();
+            for _ in 0..1000 {
+                writeln!(file, "{}", "x".repeat(99)).unwrap();
+            }
+
+            let token = CancellationToken::new();
+            let mut limit_reader = LimitReader::new();
+            limit_reader.limit(1 << 20).cancel_token(token.clone());
+            let mut lines = limit_reader
+                .read_lines(file_path.clone(), &LineLimits::new())
+                .unwrap();
+            assert_eq!(lines.next_line().unwrap().unwrap().len(), 99);
+            token.cancel();
+            let err = lines.find_map(Result::err).unwrap();
+            assert_eq!(err.kind(), ErrorKind::Cancelled);
+            assert!(lines.line_number() < 1000);
+
+            let mut e = ZlibEncoder::new(Vec::new(), Compression::best());
+            e.write_all(&[b'\n'; 1 << 20]).unwrap();
+            std::fs::write(&file_path, e.finish().unwrap()).unwrap();
+
+            let mut limit_reader = LimitReader::new();
+            limit_reader
+                .limit(1 << 30)
+                .enable_decode_zlib()
+                .max_ratio(100.0);
+            let err = limit_reader
+                .read_lines_limited(file_path, &LineLimits::new())
+                .unwrap()
+                .find_map(Result::err)
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::RatioExceeded);
+
+            drop(file);
+            dir.close().unwrap();
+        }
+
+        #[test]
+        fn sizes_beyond_the_buffer_fail_buffered_reads() {
+            let dir = tempdir().unwrap();
+
+            let file_path = dir.path().join("test_output.txt");
+            let mut file = File::create(&file_path).unwrap();
+            file.write_all(&[b'x'; 2000]).unwrap();
+
+            let mut limit_reader = LimitReader::new();
+            limit_reader.exact_size(2000);
+            let err = limit_reader.read(file_path.clone()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+            let err = limit_reader.read_limited(file_path.clone()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+            let output = limit_reader
+                .copy(File::open(&file_path).unwrap(), &mut Vec::new())
+                .unwrap();
+            assert_eq!(output.bytes_read(), 2000);
+
+            // The limit allows the source, but the buffer cannot hold it.
+            let mut file = File::create(&file_path).unwrap();
+            file.write_all(&[b'x'; 1500]).unwrap();
+            let mut limit_reader = LimitReader::new();
+            limit_reader.limit(2000);
+            let err = limit_reader.read(file_path.clone()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+
+            let mut file = File::create(&file_path).unwrap();
+            file.write_all(&[b'x'; 1024]).unwrap();
+            assert_eq!(limit_reader.read(file_path).unwrap(), 1024);
+
+            drop(file);
+            dir.close().unwrap();
+        }
+
+        #[test]
+        fn max_ratio_stops_decompression_bomb() {
+            let mut e = ZlibEncoder::new(Vec::new(), Compression::best());
+            e.write_all(&[0; 1 << 20]).unwrap();
+            let bomb = e.finish().unwrap();
+
+            let mut out = Vec::new();
+            let mut limit_reader = LimitReader::new();
+            limit_reader
+                .limit(1 << 30)
+                .enable_decode_auto()
+                .max_ratio(100.0);
+            let err = limit_reader
+                .copy_limited(bomb.as_slice(), &mut out)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::RatioExceeded);
+            let exceeded = err.get_ref().downcast_ref::<RatioExceededError>().unwrap();
+            assert!(exceeded.decoded_bytes() > 100 * exceeded.compressed_bytes());
+            assert!(out.len() < 1 << 20);
+        }
+
+        #[test]
+        fn strict_readers_enforce_ratio_and_min_size() {
+            use crate::error::LimitReaderError;
+            use std::io::Read;
+
+            let mut e = ZlibEncoder::new(Vec::new(), Compression::best());
+            e.write_all(&[0; 1 << 20]).unwrap();
+            let bomb = e.finish().unwrap();
+
+            let mut limit_reader = LimitReader::new();
+            limit_reader
+                .limit(1 << 30)
+                .enable_decode_auto()
+                .max_ratio(100.0);
+            let err = limit_reader
+                .strict_reader(bomb.as_slice())
+                .unwrap()
+                .read_to_end(&mut Vec::new())
+                .unwrap_err();
+            let err = LimitReaderError::from_io(err, ErrorKind::ReadError);
+            assert_eq!(err.kind(), ErrorKind::RatioExceeded);
+
+            let mut limit_reader = LimitReader::new();
+            limit_reader.exact_size(24);
+            let err = limit_reader
+                .strict_reader(&b"Mike was here. Briefly."[..])
+                .unwrap()
+                .read_to_end(&mut Vec::new())
+                .unwrap_err();
+            let err = LimitReaderError::from_io(err, ErrorKind::ReadError);
+            assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+            let eof = err.get_ref().downcast_ref::<UnexpectedEofError>().unwrap();
+            assert_eq!((eof.expected(), eof.actual()), (24, 23));
+
+            let mut bytes = Vec::new();
+            limit_reader
+                .exact_size(23)
+                .strict_reader(&b"Mike was here. Briefly."[..])
+                .unwrap()
+                .read_to_end(&mut bytes)
+                .unwrap();
+            assert_eq!(bytes.len(), 23);
+        }
+
+        #[cfg(feature = "serde")]
+        #[test]
+        fn output_round_trips_through_json() {
+            let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
+            e.write_all(b"Mike was here. Briefly.").unwrap();
+            let compressed = e.finish().unwrap();
+
+            let mut limit_reader = LimitReader::new();
+            let output = limit_reader
+                .enable_decode_auto()
+                .limit(14)
+                .copy_limited(compressed.as_slice(), &mut Vec::new())
+                .unwrap();
+            assert!(output.truncated());
+            assert_eq!(output.compressed_bytes(), compressed.len() as u64);
+
+            let json = serde_json::to_value(&output).unwrap();
+            assert_eq!(json["bytes_read"], 14);
+            assert_eq!(json["truncated"], true);
+            assert_eq!(json["encoding"], "zlib");
+
+            let decoded: crate::LimitReaderOutput = serde_json::from_value(json).unwrap();
+            assert_eq!(decoded.bytes_read(), 14);
+            assert_eq!(decoded.reader_count(), output.reader_count());
+            assert_eq!(decoded.encoding(), output.encoding());
+        }
     }
 
     mod infalible {
@@ -382,6 +1414,52 @@ mod tests {
             dir.close().unwrap();
         }
 
+        #[test]
+        fn remaining_bytes_are_only_known_for_plain_files() {
+            let dir = tempdir().unwrap();
+
+            let text = "Mike was here. Briefly.";
+            let file_path = dir.path().join("test_output.txt");
+            let mut file = File::create(&file_path).unwrap();
+            write!(file, "{}", &text).unwrap();
+
+            let mut limit_reader = LimitReader::new();
+            limit_reader.limit(8);
+            let output = limit_reader.read_limited(file_path.clone()).unwrap();
+            assert!(output.truncated());
+            assert_eq!(output.bytes_remaining(), Some(15));
+            assert_eq!(output.compressed_remaining(), Some(15));
+
+            // The decoded size exceeds the compressed size.
+            let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
+            e.write_all(&[b'x'; 500]).unwrap();
+            let compressed = e.finish().unwrap();
+            let mut file = File::create(&file_path).unwrap();
+            file.write_all(&compressed).unwrap();
+
+            let mut limit_reader = LimitReader::new();
+            limit_reader.limit(100).enable_decode_zlib();
+            let output = limit_reader.read_limited(file_path.clone()).unwrap();
+            assert_eq!(output.bytes_read(), 100);
+            assert_eq!(output.compressed_bytes(), compressed.len() as u64);
+            assert_eq!(output.bytes_remaining(), None);
+            assert_eq!(output.compressed_remaining(), None);
+
+            limit_reader.limit(1000);
+            let output = limit_reader.read_limited(file_path).unwrap();
+            assert_eq!(output.bytes_read(), 500);
+            assert_eq!(output.compressed_remaining(), Some(0));
+
+            let output = limit_reader
+                .copy_limited(compressed.as_slice(), &mut Vec::new())
+                .unwrap();
+            assert_eq!(output.source_size(), compressed.len() as u64);
+            assert_eq!(output.compressed_remaining(), None);
+
+            drop(file);
+            dir.close().unwrap();
+        }
+
         #[test]
         fn panic_decode_zlib_error_on_corrupt_deflate_stream() {
             let dir = tempdir().unwrap();
@@ -406,5 +1484,30 @@ mod tests {
             drop(file);
             dir.close().unwrap();
         }
+
+        #[test]
+        fn buf_read_split_truncates_at_limit() {
+            let text = "Mike,was,here.";
+            let reader = LimitReaderInfallible::new(text.as_bytes(), 10);
+
+            let parts: Vec<Vec<u8>> = reader.split(b',').map(|part| part.unwrap()).collect();
+            assert_eq!(
+                parts,
+                vec![b"Mike".to_vec(), b"was".to_vec(), b"h".to_vec()]
+            );
+        }
+
+        #[test]
+        fn read_after_fill_buf_drains_buffer_first() {
+            let text = "Mike was here.";
+            let mut reader = LimitReaderInfallible::new(text.as_bytes(), 8);
+
+            assert_eq!(reader.fill_buf().unwrap(), b"Mike was");
+            reader.consume(5);
+
+            let mut rest = Vec::new();
+            std::io::Read::read_to_end(&mut reader, &mut rest).unwrap();
+            assert_eq!(rest, b"was");
+        }
     }
 }
@@ -0,0 +1,317 @@
+use crate::error::{ErrorKind, LimitReaderError};
+use crate::LimitReaderResult as Result;
+use std::fmt::{self, Display, Formatter};
+use std::io::BufRead;
+
+/// What to do when a [`LineLimits`] limit is exceeded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LinePolicy {
+    /// Silently truncate: an over-long line is cut at `max_line_len` (the rest of it is skipped), and reading stops after `max_lines`.
+    Truncate,
+    /// Fail with a [`LineLimitError`], reported as [`ErrorKind::LineLimitExceeded`].
+    #[default]
+    Fail,
+}
+
+/// Line-oriented limits applied by a [`LineReader`].
+///
+/// Both limits are disabled by default.
+#[derive(Debug, Default, Clone)]
+pub struct LineLimits {
+    max_line_len: Option<(usize, LinePolicy)>,
+    max_lines: Option<(usize, LinePolicy)>,
+}
+
+impl LineLimits {
+    /// Create a new [`LineLimits`] with no limits configured.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit each line to `max_line_len` bytes, excluding the trailing newline.
+    pub fn max_line_len(&mut self, max_line_len: usize, policy: LinePolicy) -> &mut Self {
+        self.max_line_len = Some((max_line_len, policy));
+
+        self
+    }
+
+    /// Limit the number of lines read to `max_lines`.
+    pub fn max_lines(&mut self, max_lines: usize, policy: LinePolicy) -> &mut Self {
+        self.max_lines = Some((max_lines, policy));
+
+        self
+    }
+}
+
+/// The limit violated by a line; see [`LineLimitError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineViolation {
+    /// The line was longer than `max_line_len` bytes.
+    LineTooLong {
+        /// The configured maximum line length.
+        max_line_len: usize,
+    },
+    /// The input held more than `max_lines` lines.
+    TooManyLines {
+        /// The configured maximum number of lines.
+        max_lines: usize,
+    },
+}
+
+/// Structured error raised by a [`LineReader`] when a limit configured with [`LinePolicy::Fail`] is exceeded.
+///
+/// Retrieve it from a [`LimitReaderError`] of kind [`ErrorKind::LineLimitExceeded`] via `LimitReaderError::get_ref()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineLimitError {
+    line_number: usize,
+    violation: LineViolation,
+}
+
+impl LineLimitError {
+    /// The 1-based number of the offending line.
+    #[must_use]
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// The limit that was violated.
+    #[must_use]
+    pub fn violation(&self) -> LineViolation {
+        self.violation
+    }
+}
+
+impl Display for LineLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.violation {
+            LineViolation::LineTooLong { max_line_len } => write!(
+                f,
+                "line {} exceeds the maximum line length of {} bytes",
+                self.line_number, max_line_len
+            ),
+            LineViolation::TooManyLines { max_lines } => write!(
+                f,
+                "line {} exceeds the maximum of {} lines",
+                self.line_number, max_lines
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LineLimitError {}
+
+/// Reads newline-delimited lines from a [`BufRead`], such as the limit adapters, while enforcing [`LineLimits`].
+///
+/// Lines are yielded as bytes without the trailing `\n`.  An over-long line is never buffered beyond `max_line_len` bytes, so a single line without a newline cannot exhaust memory.
+pub struct LineReader<R>
+where
+    R: BufRead,
+{
+    reader: R,
+    limits: LineLimits,
+    line_number: usize,
+    done: bool,
+}
+
+impl<R> LineReader<R>
+where
+    R: BufRead,
+{
+    /// Wrap `reader`, enforcing `limits` on the lines read from it.
+    pub fn new(reader: R, limits: &LineLimits) -> Self {
+        Self {
+            reader,
+            limits: limits.clone(),
+            line_number: 0,
+            done: false,
+        }
+    }
+
+    /// Number of lines read so far.
+    #[must_use]
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Read the next line, returning `None` at the end of input.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::LineLimitExceeded`] if a limit configured with [`LinePolicy::Fail`] is exceeded, or the error of the underlying reader.  No further lines are returned after an error.
+    pub fn next_line(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let result = self.try_next_line();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+
+        result
+    }
+
+    fn try_next_line(&mut self) -> Result<Option<Vec<u8>>> {
+        if let Some((max_lines, policy)) = self.limits.max_lines {
+            if self.line_number >= max_lines {
+                if self.reader.fill_buf()?.is_empty() || policy == LinePolicy::Truncate {
+                    return Ok(None);
+                }
+
+                return Err(self.violation(LineViolation::TooManyLines { max_lines }));
+            }
+        }
+
+        let mut line = Vec::new();
+        let mut bytes_seen = 0;
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                if bytes_seen == 0 {
+                    return Ok(None);
+                }
+                break;
+            }
+
+            let (chunk, used, found_newline) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (&available[..i], i + 1, true),
+                None => (available, available.len(), false),
+            };
+
+            match self.limits.max_line_len {
+                Some((max_line_len, policy)) if line.len() + chunk.len() > max_line_len => {
+                    if policy == LinePolicy::Fail {
+                        return Err(self.violation(LineViolation::LineTooLong { max_line_len }));
+                    }
+                    let room = max_line_len - line.len();
+                    line.extend_from_slice(&chunk[..room]);
+                }
+                _ => line.extend_from_slice(chunk),
+            }
+
+            bytes_seen += used;
+            self.reader.consume(used);
+            if found_newline {
+                break;
+            }
+        }
+        self.line_number += 1;
+
+        Ok(Some(line))
+    }
+
+    fn violation(&self, violation: LineViolation) -> LimitReaderError {
+        LimitReaderError::new(
+            ErrorKind::LineLimitExceeded,
+            LineLimitError {
+                line_number: self.line_number + 1,
+                violation,
+            },
+        )
+    }
+}
+
+impl<R> Iterator for LineReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_line().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LimitReader, LimitReaderInfallible};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn collect(reader: LineReader<impl BufRead>) -> Vec<Vec<u8>> {
+        reader.map(|line| line.unwrap()).collect()
+    }
+
+    #[test]
+    fn truncates_long_lines_and_line_count() {
+        let text = "first line\nsecond line is long\nthird\nfourth\n";
+        let mut limits = LineLimits::new();
+        limits
+            .max_line_len(6, LinePolicy::Truncate)
+            .max_lines(3, LinePolicy::Truncate);
+
+        let lines = collect(LineReader::new(text.as_bytes(), &limits));
+        assert_eq!(
+            lines,
+            vec![b"first ".to_vec(), b"second".to_vec(), b"third".to_vec()]
+        );
+    }
+
+    #[test]
+    fn fails_with_line_number() {
+        let text = "short\nthis line is too long\n";
+        let mut limits = LineLimits::new();
+        limits.max_line_len(8, LinePolicy::Fail);
+
+        let mut reader = LineReader::new(text.as_bytes(), &limits);
+        assert_eq!(reader.next_line().unwrap(), Some(b"short".to_vec()));
+
+        let err = reader.next_line().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LineLimitExceeded);
+        let line_err = err.get_ref().downcast_ref::<LineLimitError>().unwrap();
+        assert_eq!(line_err.line_number(), 2);
+        assert_eq!(
+            line_err.violation(),
+            LineViolation::LineTooLong { max_line_len: 8 }
+        );
+        assert!(reader.next_line().unwrap().is_none());
+
+        let mut limits = LineLimits::new();
+        limits.max_lines(1, LinePolicy::Fail);
+        let mut reader = LineReader::new(text.as_bytes(), &limits);
+        reader.next_line().unwrap();
+        let err = reader.next_line().unwrap_err();
+        assert_eq!(
+            "Error: line 2 exceeds the maximum of 1 lines",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn works_on_top_of_limit_adapters() {
+        let text = "Mike was here.\nBriefly.\n";
+        let reader = LimitReaderInfallible::new(text.as_bytes(), 18);
+
+        let lines = collect(LineReader::new(reader, &LineLimits::new()));
+        assert_eq!(lines, vec![b"Mike was here.".to_vec(), b"Bri".to_vec()]);
+    }
+
+    #[test]
+    fn reads_gzip_decoded_lines() {
+        let dir = tempdir().unwrap();
+
+        let mut e = GzEncoder::new(Vec::new(), Compression::default());
+        e.write_all(b"Mike was here.\nBriefly.\n").unwrap();
+        let compressed = e.finish().unwrap();
+
+        let file_path = dir.path().join("test_output.txt.gz");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(&compressed).unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader.enable_decode_gzip();
+        let mut limits = LineLimits::new();
+        limits.max_line_len(4, LinePolicy::Truncate);
+
+        let lines = collect(limit_reader.read_lines(file_path, &limits).unwrap());
+        assert_eq!(lines, vec![b"Mike".to_vec(), b"Brie".to_vec()]);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+}
@@ -0,0 +1,309 @@
+use crate::error::{BoxError, ErrorKind, LimitReaderError};
+use crate::LimitReaderResult as Result;
+use std::cell::Cell;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The progress of a read, as reported to an [`Observer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Progress {
+    compressed_bytes: u64,
+    decoded_bytes: u64,
+    remaining_limit: u64,
+    finished: bool,
+}
+
+impl Progress {
+    /// Bytes consumed from the source.  Decoders read ahead, so this may run ahead of the decoded bytes.
+    #[must_use]
+    pub fn compressed_bytes(&self) -> u64 {
+        self.compressed_bytes
+    }
+
+    /// Decoded bytes accepted by the limit.
+    #[must_use]
+    pub fn decoded_bytes(&self) -> u64 {
+        self.decoded_bytes
+    }
+
+    /// Decoded bytes produced per byte consumed from the source, or `0.0` before any byte was consumed.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn ratio(&self) -> f64 {
+        if self.compressed_bytes == 0 {
+            return 0.0;
+        }
+
+        self.decoded_bytes as f64 / self.compressed_bytes as f64
+    }
+
+    /// Decoded bytes that may still be read before the limit is reached.
+    #[must_use]
+    pub fn remaining_limit(&self) -> u64 {
+        self.remaining_limit
+    }
+
+    /// Whether the read completed; the last report of each successful read is final.
+    #[must_use]
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Observes the progress of reads by [`crate::LimitReader`], e.g. to drive a progress bar or log.  Set with [`crate::LimitReader::observe`].
+///
+/// Returning an error aborts the read, so policies beyond byte counts can be plugged in.  A `LimitReaderError` keeps its kind, any other error is reported as [`ErrorKind::Vetoed`].
+pub trait Observer: Send {
+    /// Called as bytes flow through the reader.
+    ///
+    /// # Errors
+    ///
+    /// Return an error to abort the read.
+    fn on_progress(&mut self, progress: &Progress) -> std::result::Result<(), BoxError>;
+}
+
+impl<F> Observer for F
+where
+    F: FnMut(&Progress) -> std::result::Result<(), BoxError> + Send,
+{
+    fn on_progress(&mut self, progress: &Progress) -> std::result::Result<(), BoxError> {
+        self(progress)
+    }
+}
+
+/// An [`Observer`], notified at most once every `every_bytes` decoded bytes or `every` interval.
+pub(crate) struct Observation {
+    observer: Box<dyn Observer>,
+    every_bytes: u64,
+    every: Duration,
+    last_bytes: u64,
+    last_at: Option<Instant>,
+}
+
+impl Observation {
+    pub(crate) fn new(
+        observer: impl Observer + 'static,
+        every_bytes: u64,
+        every: Duration,
+    ) -> Self {
+        Self {
+            observer: Box::new(observer),
+            every_bytes,
+            every,
+            last_bytes: 0,
+            last_at: None,
+        }
+    }
+
+    /// Start afresh for a new read, as the last one may have failed before finishing.
+    pub(crate) fn start(&mut self) {
+        self.last_bytes = 0;
+        self.last_at = None;
+    }
+
+    /// Report progress if enough bytes or time passed since the last report.
+    pub(crate) fn update(
+        &mut self,
+        now: Instant,
+        compressed_bytes: u64,
+        decoded_bytes: u64,
+        remaining_limit: u64,
+    ) -> Result<()> {
+        let last_at = *self.last_at.get_or_insert(now);
+        if decoded_bytes.saturating_sub(self.last_bytes) < self.every_bytes
+            && now.saturating_duration_since(last_at) < self.every
+        {
+            return Ok(());
+        }
+        self.last_bytes = decoded_bytes;
+        self.last_at = Some(now);
+
+        self.notify(Progress {
+            compressed_bytes,
+            decoded_bytes,
+            remaining_limit,
+            finished: false,
+        })
+    }
+
+    /// Report the final progress of a read.
+    pub(crate) fn finish(
+        &mut self,
+        compressed_bytes: u64,
+        decoded_bytes: u64,
+        remaining_limit: u64,
+    ) -> Result<()> {
+        self.notify(Progress {
+            compressed_bytes,
+            decoded_bytes,
+            remaining_limit,
+            finished: true,
+        })
+    }
+
+    fn notify(&mut self, progress: Progress) -> Result<()> {
+        self.observer.on_progress(&progress).map_err(|err| {
+            match err.downcast::<LimitReaderError>() {
+                Ok(err) => *err,
+                Err(err) => LimitReaderError::new(ErrorKind::Vetoed, err),
+            }
+        })
+    }
+}
+
+/// Counts the bytes read through it.
+pub(crate) struct CountingReader<'a, R> {
+    inner: R,
+    count: &'a Cell<u64>,
+}
+
+impl<'a, R: Read> CountingReader<'a, R> {
+    pub(crate) fn new(inner: R, count: &'a Cell<u64>) -> Self {
+        Self { inner, count }
+    }
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+
+        Ok(n)
+    }
+}
+
+// Like `CountingReader`, sharing the count with a limit adapter that outlives the read.
+pub(crate) struct SharedCountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> SharedCountingReader<R> {
+    pub(crate) fn new(inner: R, count: Arc<AtomicU64>) -> Self {
+        Self { inner, count }
+    }
+}
+
+impl<R: Read> Read for SharedCountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LimitReader, ManualClock};
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::fs::File;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    #[test]
+    fn reports_progress_every_n_bytes() {
+        let dir = tempdir().unwrap();
+
+        let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
+        e.write_all(&[b'x'; 1000]).unwrap();
+        let compressed = e.finish().unwrap();
+
+        let file_path = dir.path().join("test_output.txt.z");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(&compressed).unwrap();
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let observed = Arc::clone(&reports);
+        let mut limit_reader = LimitReader::new();
+        limit_reader
+            .enable_decode_zlib()
+            // Chunk the read into 100 byte reads.
+            .rate_limit(100, 100)
+            .clock(Arc::new(ManualClock::new()))
+            .observe(
+                move |progress: &Progress| {
+                    observed.lock().unwrap().push(*progress);
+                    Ok(())
+                },
+                200,
+                Duration::from_secs(3600),
+            );
+        limit_reader.read(file_path).unwrap();
+
+        let reports = reports.lock().unwrap();
+        let decoded: Vec<_> = reports.iter().map(Progress::decoded_bytes).collect();
+        assert_eq!(decoded, [200, 400, 600, 800, 1000, 1000]);
+        let last = reports.last().unwrap();
+        assert!(last.finished());
+        assert_eq!(last.compressed_bytes(), compressed.len() as u64);
+        assert!(last.ratio() > 10.0);
+        assert_eq!(last.remaining_limit(), 23);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn observer_vetoes_read() {
+        let dir = tempdir().unwrap();
+
+        let file_path = dir.path().join("test_output.txt");
+        let mut file = File::create(&file_path).unwrap();
+        write!(file, "Mike was here. Briefly.").unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader.observe(|_: &Progress| Err("not today".into()), 0, Duration::ZERO);
+        let err = limit_reader.read(file_path.clone()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Vetoed);
+        assert_eq!(err.to_string(), "Error: not today");
+
+        limit_reader.observe(
+            |_: &Progress| Err(LimitReaderError::new(ErrorKind::LimitExceeded, "quota").into()),
+            0,
+            Duration::ZERO,
+        );
+        let err = limit_reader.read(file_path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn reuses_reader_after_failed_read() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let observed = Arc::clone(&reports);
+        let mut limit_reader = LimitReader::new();
+        limit_reader
+            .limit(100)
+            // Chunk the read into 10 byte reads.
+            .rate_limit(10, 10)
+            .clock(Arc::new(ManualClock::new()))
+            .observe(
+                move |progress: &Progress| {
+                    observed.lock().unwrap().push(*progress);
+                    Ok(())
+                },
+                10,
+                Duration::from_secs(3600),
+            );
+        let err = limit_reader
+            .copy(&[b'x'; 200][..], &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+
+        reports.lock().unwrap().clear();
+        limit_reader.copy(&b"Mike."[..], &mut Vec::new()).unwrap();
+        let reports = reports.lock().unwrap();
+        let decoded: Vec<_> = reports.iter().map(Progress::decoded_bytes).collect();
+        assert_eq!(decoded, [5]);
+        assert!(reports.last().unwrap().finished());
+    }
+}
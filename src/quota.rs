@@ -0,0 +1,377 @@
+use crate::budget::BudgetExceededError;
+use crate::error::{ErrorKind, LimitReaderError};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+/// A node of a quota tree, e.g. a request within a tenant within the process-wide pool.  Bytes charged to a node are charged to all of its ancestors, so a read is bounded by the tightest of them.
+///
+/// Clones refer to the same node.  Exceeding a node fails the read with [`ErrorKind::BudgetExceeded`], naming the node by its path; in truncating mode, the read is truncated instead.
+#[derive(Debug, Clone)]
+pub struct Quota(Arc<QuotaNode>);
+
+#[derive(Debug)]
+struct QuotaNode {
+    name: String,
+    limit: u64,
+    used: AtomicU64,
+    parent: Option<Quota>,
+    children: Mutex<Vec<Weak<QuotaNode>>>,
+}
+
+/// Usage of a [`Quota`] node at one point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuotaSnapshot {
+    path: String,
+    limit: u64,
+    used: u64,
+}
+
+impl QuotaSnapshot {
+    /// The names of the node and its ancestors, from the root, separated by `/`.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The total bytes allowed.
+    #[must_use]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Bytes charged by the reads so far.
+    #[must_use]
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+}
+
+impl Quota {
+    /// Create the root of a quota tree, allowing `limit` bytes.
+    pub fn root(name: impl Into<String>, limit: u64) -> Self {
+        Self::new(name.into(), limit, None)
+    }
+
+    /// Create a child node allowing `limit` bytes, which are also charged to this node.
+    ///
+    /// # Panics
+    ///
+    /// If another thread panicked while creating a child of this node.
+    #[must_use]
+    pub fn child(&self, name: impl Into<String>, limit: u64) -> Self {
+        let child = Self::new(name.into(), limit, Some(self.clone()));
+        let mut children = self.0.children.lock().expect("quota lock poisoned");
+        children.retain(|child| child.strong_count() > 0);
+        children.push(Arc::downgrade(&child.0));
+
+        child
+    }
+
+    fn new(name: String, limit: u64, parent: Option<Quota>) -> Self {
+        Self(Arc::new(QuotaNode {
+            name,
+            limit,
+            used: AtomicU64::new(0),
+            parent,
+            children: Mutex::new(Vec::new()),
+        }))
+    }
+
+    /// The name of the node.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// The names of the node and its ancestors, from the root, separated by `/`.
+    #[must_use]
+    pub fn path(&self) -> String {
+        match &self.0.parent {
+            Some(parent) => format!("{}/{}", parent.path(), self.0.name),
+            None => self.0.name.clone(),
+        }
+    }
+
+    /// Usage of this node.
+    #[must_use]
+    pub fn snapshot(&self) -> QuotaSnapshot {
+        QuotaSnapshot {
+            path: self.path(),
+            limit: self.0.limit,
+            used: self.0.used.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Usage of this node and all of its live descendants, depth-first.
+    ///
+    /// # Panics
+    ///
+    /// If another thread panicked while creating a child of a node.
+    #[must_use]
+    pub fn snapshot_tree(&self) -> Vec<QuotaSnapshot> {
+        let mut snapshots = vec![self.snapshot()];
+        let children: Vec<_> = self
+            .0
+            .children
+            .lock()
+            .expect("quota lock poisoned")
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for child in children {
+            snapshots.extend(Quota(child).snapshot_tree());
+        }
+
+        snapshots
+    }
+
+    // This node followed by its ancestors.
+    fn nodes(&self) -> impl Iterator<Item = &Quota> {
+        std::iter::successors(Some(self), |quota| quota.0.parent.as_ref())
+    }
+
+    /// Charge `bytes` to this node and its ancestors, or to none of them if that would exceed any.
+    pub(crate) fn charge(&self, bytes: u64) -> Result<(), BudgetExceededError> {
+        for (charged, quota) in self.nodes().enumerate() {
+            let limit = quota.0.limit;
+            let result = quota
+                .0
+                .used
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                    used.checked_add(bytes).filter(|&total| total <= limit)
+                });
+            if result.is_err() {
+                self.nodes()
+                    .take(charged)
+                    .for_each(|quota| quota.release(bytes));
+
+                return Err(BudgetExceededError {
+                    budget: quota.path(),
+                    limit,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Bytes that may still be charged to this node and its ancestors.
+    fn available(&self) -> u64 {
+        self.nodes()
+            .map(|quota| {
+                quota
+                    .0
+                    .limit
+                    .saturating_sub(quota.0.used.load(Ordering::Relaxed))
+            })
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Charge as many bytes as possible, up to `want`, returning the number charged.
+    pub(crate) fn reserve(&self, want: u64) -> u64 {
+        loop {
+            let available = self.available().min(want);
+            if available == 0 || self.charge(available).is_ok() {
+                return available;
+            }
+        }
+    }
+
+    fn release(&self, bytes: u64) {
+        self.0.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+/// A [`Quota`] as consulted by a limit adapter.
+///
+/// A failing adapter charges each chunk it accepts, failing once the quota is exceeded.  A truncating adapter reads no more than the quota has left, and charges each read once it returns, so a read never holds quota it does not use.  Bytes beyond the quota, if concurrent reads used it up in the meantime, are dropped, truncating the stream.
+#[derive(Debug, Clone)]
+pub(crate) struct QuotaCharge {
+    quota: Quota,
+    truncate: bool,
+    truncated: bool,
+}
+
+impl QuotaCharge {
+    pub(crate) fn new(quota: Quota, truncate: bool) -> Self {
+        Self {
+            quota,
+            truncate,
+            truncated: false,
+        }
+    }
+
+    /// Whether a read was truncated because the quota was exhausted.
+    pub(crate) fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Record whether the stream held further bytes once the quota was exhausted.
+    pub(crate) fn exhausted(&mut self, truncated: bool) {
+        self.truncated |= truncated;
+    }
+
+    /// The number of bytes, at most `want`, to read next.  Zero means the quota is exhausted.
+    pub(crate) fn before_read(&self, want: usize) -> usize {
+        if !self.truncate {
+            return want;
+        }
+
+        usize::try_from(self.quota.available()).map_or(want, |available| available.min(want))
+    }
+
+    /// Charge the `bytes_read` bytes of a read in truncating mode, returning the number kept within the quota.
+    pub(crate) fn after_read(&mut self, bytes_read: usize) -> usize {
+        if !self.truncate {
+            return bytes_read;
+        }
+        let charged = usize::try_from(self.quota.reserve(bytes_read as u64)).unwrap_or(bytes_read);
+        if charged < bytes_read {
+            self.truncated = true;
+        }
+
+        charged
+    }
+
+    /// Charge `bytes` accepted by a failing adapter.
+    pub(crate) fn accept(&self, bytes: usize) -> io::Result<()> {
+        if self.truncate {
+            return Ok(());
+        }
+        self.quota
+            .charge(bytes as u64)
+            .map_err(|err| LimitReaderError::new(ErrorKind::BudgetExceeded, err).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LimitReader, LimitReaderFallible, LimitReaderInfallible};
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use tempfile::tempdir;
+
+    fn read_with(quota: &Quota, len: usize) -> crate::LimitReaderResult<usize> {
+        let data = vec![b'x'; len];
+        let mut reader = LimitReaderFallible::new(data.as_slice(), 1000);
+        reader.quota(quota.clone());
+        // A single read, so that a read crossing the quota charges nothing.
+        let mut buf = vec![0; len + 1];
+
+        Ok(reader.read(&mut buf)?)
+    }
+
+    fn exceeded(err: &LimitReaderError) -> &str {
+        assert_eq!(err.kind(), ErrorKind::BudgetExceeded);
+        err.get_ref()
+            .downcast_ref::<BudgetExceededError>()
+            .unwrap()
+            .budget()
+    }
+
+    #[test]
+    fn charges_every_level() {
+        let global = Quota::root("global", 1000);
+        let tenant = global.child("tenant-a", 100);
+        let first = tenant.child("req-1", 60);
+        let second = tenant.child("req-2", 100);
+
+        let err = read_with(&first, 80).unwrap_err();
+        assert_eq!(exceeded(&err), "global/tenant-a/req-1");
+        assert_eq!(read_with(&second, 80).unwrap(), 80);
+        let err = read_with(&first, 30).unwrap_err();
+        assert_eq!(exceeded(&err), "global/tenant-a");
+
+        let usage: Vec<_> = global
+            .snapshot_tree()
+            .iter()
+            .map(|snapshot| (snapshot.path().to_string(), snapshot.used()))
+            .collect();
+        assert_eq!(
+            usage,
+            [
+                ("global".to_string(), 80),
+                ("global/tenant-a".to_string(), 80),
+                ("global/tenant-a/req-1".to_string(), 0),
+                ("global/tenant-a/req-2".to_string(), 80),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncating_mode_refunds_unused_reservations() {
+        let global = Quota::root("global", 1000);
+        let request = global.child("req-1", 100);
+
+        let mut reader = LimitReaderInfallible::new([b'x'; 23].as_slice(), 1000);
+        reader.quota(request.clone());
+        assert_eq!(reader.read_to_end(&mut Vec::new()).unwrap(), 23);
+        assert!(!reader.truncated());
+        assert_eq!(request.snapshot().used(), 23);
+        assert_eq!(global.snapshot().used(), 23);
+
+        let mut reader = LimitReaderInfallible::new([b'x'; 100].as_slice(), 1000);
+        reader.quota(request.clone());
+        assert_eq!(reader.read_to_end(&mut Vec::new()).unwrap(), 77);
+        assert!(reader.truncated());
+        assert_eq!(global.snapshot().used(), 100);
+    }
+
+    #[test]
+    fn charges_only_bytes_delivered() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test_output.txt");
+        File::create(&file_path)
+            .unwrap()
+            .write_all(&[b'x'; 100_000])
+            .unwrap();
+
+        let request = Quota::root("req-1", 1 << 20);
+        let mut limit_reader = LimitReader::new();
+        limit_reader.limit(1 << 20).quota(request.clone());
+        let output = limit_reader.read_limited(file_path).unwrap();
+        assert_eq!(output.bytes_read(), LimitReader::DEFAULT_BUF_SIZE as u64);
+        assert!(output.truncated());
+        assert_eq!(request.snapshot().used(), output.bytes_read());
+
+        dir.close().unwrap();
+    }
+
+    // Records the quota used by the time each read is issued.
+    struct Spy {
+        quota: Quota,
+        used: Vec<u64>,
+        left: usize,
+    }
+
+    impl Read for Spy {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.used.push(self.quota.snapshot().used());
+            let n = buf.len().min(self.left).min(10);
+            self.left -= n;
+
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn truncating_mode_charges_only_bytes_read() {
+        let request = Quota::root("req-1", 30);
+        let mut spy = Spy {
+            quota: request.clone(),
+            used: Vec::new(),
+            left: 30,
+        };
+        let mut reader = LimitReaderInfallible::new(&mut spy, 1000);
+        reader.quota(request.clone());
+        assert_eq!(reader.read_to_end(&mut Vec::new()).unwrap(), 30);
+        // The source ended just as the quota did.
+        assert!(!reader.truncated());
+        assert_eq!(spy.used, [0, 10, 20, 30]);
+        assert_eq!(request.snapshot().used(), 30);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::clock::Clock;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tokio")]
+use crate::clock::SystemClock;
+#[cfg(feature = "tokio")]
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, ReadBuf};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// A token bucket granting one token per byte, refilled at `rate` bytes per second up to `burst` tokens.
+#[derive(Debug, Clone)]
+pub(crate) struct TokenBucket {
+    rate: u64,
+    burst: u64,
+    tokens: u64,
+    last_refill: Option<Instant>,
+    throttled: Duration,
+}
+
+impl TokenBucket {
+    /// A full bucket.  Both `rate` and `burst` are raised to at least 1.
+    pub(crate) fn new(rate: u64, burst: u64) -> Self {
+        let burst = burst.max(1);
+
+        Self {
+            rate: rate.max(1),
+            burst,
+            tokens: burst,
+            last_refill: None,
+            throttled: Duration::ZERO,
+        }
+    }
+
+    /// Total time spent waiting for tokens.
+    pub(crate) fn throttled(&self) -> Duration {
+        self.throttled
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let last = *self.last_refill.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(last).as_nanos();
+        let new_tokens = elapsed * u128::from(self.rate) / NANOS_PER_SEC;
+        if new_tokens == 0 {
+            return;
+        }
+
+        self.tokens = u64::try_from(u128::from(self.tokens) + new_tokens)
+            .unwrap_or(u64::MAX)
+            .min(self.burst);
+        // Carry over the time towards the next token, unless the bucket is full.
+        self.last_refill = if self.tokens == self.burst {
+            Some(now)
+        } else {
+            let spent = new_tokens * NANOS_PER_SEC / u128::from(self.rate);
+            Some(last + Duration::from_nanos(u64::try_from(spent).unwrap_or(u64::MAX)))
+        };
+    }
+
+    /// The number of bytes, at most `want`, that may be read now.  An empty bucket returns how long to wait for `want` bytes (capped to the burst) instead.
+    pub(crate) fn poll_acquire(&mut self, now: Instant, want: usize) -> Result<usize, Duration> {
+        if want == 0 {
+            return Ok(0);
+        }
+        self.refill(now);
+        if self.tokens > 0 {
+            return Ok(usize::try_from(self.tokens).map_or(want, |tokens| tokens.min(want)));
+        }
+
+        let want = u64::try_from(want).map_or(self.burst, |want| want.min(self.burst));
+        let nanos = (u128::from(want) * NANOS_PER_SEC).div_ceil(u128::from(self.rate));
+
+        Err(Duration::from_nanos(
+            u64::try_from(nanos).unwrap_or(u64::MAX),
+        ))
+    }
+
+    /// Like [`TokenBucket::poll_acquire`], sleeping on `clock` until bytes may be read.
+    pub(crate) fn acquire(&mut self, clock: &dyn Clock, want: usize) -> usize {
+        loop {
+            match self.poll_acquire(clock.now(), want) {
+                Ok(len) => return len,
+                Err(delay) => {
+                    clock.sleep(delay);
+                    self.throttled += delay;
+                }
+            }
+        }
+    }
+
+    /// Take tokens for `bytes_read` bytes.
+    pub(crate) fn consume(&mut self, bytes_read: usize) {
+        self.tokens = self.tokens.saturating_sub(bytes_read as u64);
+    }
+}
+
+/// Limits the throughput of an [`AsyncRead`] with a token bucket, waiting on a timer rather than blocking while throttled.
+///
+/// The blocking equivalent is the `rate_limit` option of [`crate::LimitReader`] and the limit adapters.  Like [`crate::LimitReader::rate_limit`], which limits the decoded source, this limits the bytes read from the wrapped reader: wrap a decoder to limit decoded bytes, or the compressed source to limit the bytes received.
+#[cfg(feature = "tokio")]
+pub struct AsyncRateLimitReader<R> {
+    inner: R,
+    bucket: TokenBucket,
+    clock: Option<Arc<dyn Clock>>,
+    // The pending wait for tokens, with its duration.
+    sleep: Option<(Pin<Box<tokio::time::Sleep>>, Duration)>,
+}
+
+#[cfg(feature = "tokio")]
+impl<R: AsyncRead + Unpin> AsyncRateLimitReader<R> {
+    /// Wrap `inner`, reading at most `bytes_per_second` on average, and at most `burst` bytes at once.
+    pub fn new(inner: R, bytes_per_second: u64, burst: u64) -> Self {
+        Self {
+            inner,
+            bucket: TokenBucket::new(bytes_per_second, burst),
+            clock: None,
+            sleep: None,
+        }
+    }
+
+    /// Refill the bucket according to `clock` rather than the [`SystemClock`].  Waiting always uses the `tokio` timer.
+    pub fn clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = Some(clock);
+
+        self
+    }
+
+    /// Total time spent throttled, counting each wait for tokens once it is over.
+    #[must_use]
+    pub fn throttled(&self) -> Duration {
+        self.bucket.throttled()
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncRateLimitReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let Some((sleep, delay)) = &mut this.sleep {
+                ready!(sleep.as_mut().poll(cx));
+                this.bucket.throttled += *delay;
+                this.sleep = None;
+            }
+
+            let now = this.clock.as_deref().unwrap_or(&SystemClock).now();
+            match this.bucket.poll_acquire(now, buf.remaining()) {
+                Ok(len) => {
+                    let mut limited = ReadBuf::new(buf.initialize_unfilled_to(len));
+                    ready!(Pin::new(&mut this.inner).poll_read(cx, &mut limited))?;
+                    let bytes_read = limited.filled().len();
+                    this.bucket.consume(bytes_read);
+                    buf.advance(bytes_read);
+
+                    return Poll::Ready(Ok(()));
+                }
+                Err(delay) => {
+                    this.sleep = Some((Box::pin(tokio::time::sleep(delay)), delay));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LimitReader, LimitReaderFallible, ManualClock};
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn bucket_refills_at_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(10, 10);
+
+        assert_eq!(bucket.poll_acquire(start, 100), Ok(10));
+        bucket.consume(10);
+        assert_eq!(
+            bucket.poll_acquire(start, 5),
+            Err(Duration::from_millis(500))
+        );
+        assert_eq!(
+            bucket.poll_acquire(start + Duration::from_millis(250), 5),
+            Ok(2)
+        );
+        assert_eq!(
+            bucket.poll_acquire(start + Duration::from_secs(60), 100),
+            Ok(10)
+        );
+    }
+
+    #[test]
+    fn adapter_sleeps_while_throttled() {
+        let clock = Arc::new(ManualClock::new());
+        let start = clock.now();
+        let mut reader = LimitReaderFallible::new([b'x'; 30].as_slice(), 100);
+        reader.rate_limit(10, 10).clock(clock.clone());
+
+        let mut buf = [0; 30];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.throttled(), Duration::from_secs(2));
+        assert_eq!(clock.now() - start, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn limit_reader_reports_throttled_time() {
+        let dir = tempdir().unwrap();
+
+        let file_path = dir.path().join("test_output.txt");
+        let mut file = File::create(&file_path).unwrap();
+        write!(file, "Mike was here. Briefly.").unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader
+            .rate_limit(10, 10)
+            .clock(Arc::new(ManualClock::new()));
+
+        let output = limit_reader.read_limited(file_path).unwrap();
+        assert_eq!(output.bytes_read(), 23);
+        assert_eq!(output.throttled(), Duration::from_secs(2));
+        assert_eq!(limit_reader.throttled(), Duration::from_secs(2));
+
+        drop(file);
+        dir.close().unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    struct TokioClock;
+
+    #[cfg(feature = "tokio")]
+    impl Clock for TokioClock {
+        fn now(&self) -> Instant {
+            tokio::time::Instant::now().into_std()
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn async_reader_waits_on_timer() {
+        use tokio::io::AsyncReadExt;
+
+        let start = tokio::time::Instant::now();
+        let mut reader = AsyncRateLimitReader::new([b'x'; 30].as_slice(), 10, 10);
+        reader.clock(Arc::new(TokioClock));
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf.len(), 30);
+        // The final read waits for tokens before finding the end of the source.
+        assert_eq!(reader.throttled(), Duration::from_secs(3));
+        assert!(start.elapsed() >= Duration::from_secs(3));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn async_reader_counts_waits_once_over() {
+        use tokio::io::AsyncReadExt;
+
+        let mut reader = AsyncRateLimitReader::new([b'x'; 30].as_slice(), 10, 10);
+        reader.clock(Arc::new(TokioClock));
+
+        let mut buf = [0; 10];
+        reader.read_exact(&mut buf).await.unwrap();
+        // Abandon the read half way through its wait for tokens.
+        tokio::time::timeout(Duration::from_millis(500), reader.read(&mut buf))
+            .await
+            .unwrap_err();
+        assert_eq!(reader.throttled(), Duration::ZERO);
+    }
+}
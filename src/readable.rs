@@ -1,21 +1,227 @@
 #[allow(clippy::wildcard_imports)]
 use super::*;
-
-pub struct MyBufReader<Z: Read>(pub Z);
-
-impl<Z: Read> Read for MyBufReader<Z> {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.0.read(buf)
-    }
-}
+use crate::budget::SharedBudget;
+use crate::cancel::CancellationToken;
+use crate::clock::{Clock, SystemClock};
+use crate::inspect::{Inspection, Inspector};
+use crate::quota::{Quota, QuotaCharge};
+use crate::rate::TokenBucket;
+use crate::tee::{Tee, TeeStats};
+use crate::timeout::Timeouts;
+use falible::LimitReaderFallible;
+use infalible::LimitReaderInfallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 pub trait Readable {
     fn perform_read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Whether the reader stopped short of the end of the underlying stream because of its limit.
+    fn truncated(&self) -> bool;
 }
 
 #[allow(dead_code)]
 type ReaderResult<T> = std::result::Result<T, LimitReaderError>;
 
+/// Capacity of the internal buffer backing the [`BufRead`] implementations of the limit adapters.
+const DEFAULT_BUF_CAPACITY: usize = 8 * 1024;
+
+/// Internal buffer used by the limit adapters to implement [`BufRead`].
+///
+/// The buffer is only allocated on the first call to `fill_buf`, so adapters that are only used via [`Read`] do not pay for it.
+#[derive(Default)]
+struct Buffer {
+    buf: Vec<u8>,
+    pos: usize,
+    filled: usize,
+}
+
+impl Buffer {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.filled
+    }
+
+    fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+
+    /// Copy buffered bytes into `out`, returning the number of bytes copied.
+    fn copy_to(&mut self, out: &mut [u8]) -> usize {
+        let available = self.buffer();
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+
+        n
+    }
+
+    /// Refill the buffer with `read` if all buffered bytes have been consumed.
+    fn fill(&mut self, read: impl FnOnce(&mut [u8]) -> io::Result<usize>) -> io::Result<&[u8]> {
+        if self.is_empty() {
+            if self.buf.is_empty() {
+                self.buf = vec![0; DEFAULT_BUF_CAPACITY];
+            }
+            let n = read(&mut self.buf)?;
+            self.pos = 0;
+            self.filled = n;
+        }
+
+        Ok(self.buffer())
+    }
+}
+
+/// Optional behaviour shared by the limit adapters, applied around each read of the underlying reader.
+#[derive(Default)]
+pub(crate) struct Hooks {
+    pub(crate) clock: Option<Arc<dyn Clock>>,
+    pub(crate) tee: Option<Tee>,
+    pub(crate) timeouts: Timeouts,
+    pub(crate) rate: Option<TokenBucket>,
+    pub(crate) cancel: Option<CancellationToken>,
+    pub(crate) inspection: Option<Inspection>,
+    pub(crate) budget: Option<SharedBudget>,
+    pub(crate) quota: Option<QuotaCharge>,
+    // The maximum ratio, with the compressed bytes consumed by the decoder.
+    pub(crate) ratio: Option<(f64, Arc<AtomicU64>)>,
+    // The size the stream must reach before it ends, with the bytes read so far.
+    pub(crate) min_size: u64,
+    pub(crate) decoded: u64,
+}
+
+impl Hooks {
+    /// Read from `reader` into `buf`, applying the hooks.
+    fn read(&mut self, reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+        self.check_cancelled()?;
+        let clock = self.clock.as_deref().unwrap_or(&SystemClock);
+        self.timeouts.before_read(clock)?;
+        let mut len = match &mut self.rate {
+            Some(rate) => {
+                let len = rate.acquire(clock, buf.len());
+                // The read may have been cancelled while throttled.
+                self.check_cancelled()?;
+                len
+            }
+            None => buf.len(),
+        };
+        if let Some(allowed) = self.quota.as_ref().map(|quota| quota.before_read(len)) {
+            if allowed == 0 && len > 0 {
+                // The quota is exhausted, which only truncates the stream if it holds further bytes.
+                let truncated = self.truncated() || self.probe(reader)?;
+                if let Some(quota) = &mut self.quota {
+                    quota.exhausted(truncated);
+                }
+
+                return Ok(0);
+            }
+            len = allowed;
+        }
+        let mut bytes_read = reader.read(&mut buf[..len])?;
+        if let Some(rate) = &mut self.rate {
+            rate.consume(bytes_read);
+        }
+        self.timeouts.after_read(clock, bytes_read)?;
+        if let Some(quota) = &mut self.quota {
+            bytes_read = quota.after_read(bytes_read);
+        }
+        self.decoded += bytes_read as u64;
+        if let Some((max_ratio, compressed)) = &self.ratio {
+            check_ratio(*max_ratio, compressed.load(Ordering::Relaxed), self.decoded)?;
+        }
+        if bytes_read == 0 && len > 0 {
+            check_min_size(self.min_size, self.decoded)?;
+        }
+
+        Ok(bytes_read)
+    }
+
+    /// Fail if the read was cancelled.
+    fn check_cancelled(&self) -> io::Result<()> {
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Err(
+                LimitReaderError::new(error::ErrorKind::Cancelled, "read cancelled").into(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Read and discard up to `max` bytes, under the time limits and cancellation but without charging them, returning whether the reader ended within them.
+    pub(crate) fn drain(&mut self, reader: &mut impl Read, max: u64) -> io::Result<bool> {
+        let mut buf = [0; DEFAULT_BUF_CAPACITY];
+        let mut left = max;
+        loop {
+            self.check_cancelled()?;
+            let clock = self.clock.as_deref().unwrap_or(&SystemClock);
+            self.timeouts.before_read(clock)?;
+            // One byte beyond `max` tells whether the reader holds more.
+            let len = saturating_len(left.saturating_add(1)).min(buf.len());
+            let bytes_read = reader.read(&mut buf[..len])?;
+            self.timeouts.after_read(clock, bytes_read)?;
+            if bytes_read == 0 {
+                return Ok(true);
+            }
+            if bytes_read as u64 > left {
+                return Ok(false);
+            }
+            left -= bytes_read as u64;
+        }
+    }
+
+    /// Whether the underlying reader holds a further byte, which is discarded without being charged to a rate limit or quota.
+    fn probe(&mut self, reader: &mut impl Read) -> io::Result<bool> {
+        let clock = self.clock.as_deref().unwrap_or(&SystemClock);
+        self.timeouts.before_read(clock)?;
+        let bytes_read = reader.read(&mut [0; 1])?;
+        self.timeouts.after_read(clock, bytes_read)?;
+
+        Ok(bytes_read > 0)
+    }
+
+    /// Whether a quota truncated the stream.
+    fn truncated(&self) -> bool {
+        self.quota.as_ref().is_some_and(QuotaCharge::truncated)
+    }
+
+    /// Total time spent throttled by the rate limit.
+    pub(crate) fn throttled(&self) -> Duration {
+        self.rate
+            .as_ref()
+            .map_or(Duration::ZERO, TokenBucket::throttled)
+    }
+
+    /// Called with the bytes accepted by the limit.
+    fn accept(&mut self, data: &[u8]) -> io::Result<()> {
+        if let Some(budget) = &self.budget {
+            budget.charge(data.len() as u64)?;
+        }
+        if let Some(quota) = &self.quota {
+            quota.accept(data.len())?;
+        }
+        if let Some(inspection) = &mut self.inspection {
+            inspection.inspect(data)?;
+        }
+        if let Some(tee) = &mut self.tee {
+            tee.mirror(data);
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert a `u64` limit into a `usize` length, saturating on platforms where it does not fit.
+fn saturating_len(limit: u64) -> usize {
+    usize::try_from(limit).unwrap_or(usize::MAX)
+}
+
 pub(crate) mod falible {
     #[allow(clippy::wildcard_imports)]
     use super::*;
@@ -27,47 +233,184 @@ pub(crate) mod falible {
         fn perform_read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
             self.read(buf)
         }
+
+        fn truncated(&self) -> bool {
+            false
+        }
     }
 
-    pub(crate) struct LimitReaderFallible<R>
+    /// A limit adapter that returns an error once the underlying reader yields more than `limit` bytes.
+    ///
+    /// Implements both [`Read`] and [`BufRead`], so methods such as [`BufRead::read_line`], [`BufRead::lines`] and [`BufRead::split`] work directly on the limited stream.  Reads are passed on at the size requested, while [`BufRead::fill_buf`] reads ahead into an internal buffer, charging budgets and quotas for every byte it buffers.
+    pub struct LimitReaderFallible<R>
     where
         R: Read,
     {
         reader: R,
         limit: u64,
         reader_count: usize,
+        buf: Buffer,
+        hooks: Hooks,
     }
 
     impl<R> LimitReaderFallible<R>
     where
         R: Read,
     {
+        /// Wrap `r`, allowing at most `limit` bytes to be read from it.
         pub fn new(r: R, limit: u64) -> Self {
             Self {
                 reader: r,
                 limit,
                 reader_count: 0,
+                buf: Buffer::default(),
+                hooks: Hooks::default(),
             }
         }
+
+        /// Mirror every byte accepted by the limit into `writer`, dropping bytes beyond `cap`, or once `writer` fails, without affecting the read.
+        pub fn tee(&mut self, writer: impl Write + Send + 'static, cap: u64) -> &mut Self {
+            self.hooks.tee = Some(Tee::new(writer, cap));
+
+            self
+        }
+
+        /// Bytes mirrored by [`Self::tee`], if set.
+        #[must_use]
+        pub fn tee_stats(&self) -> Option<TeeStats> {
+            self.hooks.tee.as_ref().map(Tee::stats)
+        }
+
+        /// Fail with [`ErrorKind::Timeout`](crate::ErrorKind::Timeout) once reading takes longer than `deadline`, measured from the first read.
+        pub fn deadline(&mut self, deadline: Duration) -> &mut Self {
+            self.hooks.timeouts.deadline(deadline);
+
+            self
+        }
+
+        /// Fail with [`ErrorKind::Timeout`](crate::ErrorKind::Timeout) once the underlying reader yields no bytes for longer than `idle_timeout`.
+        pub fn idle_timeout(&mut self, idle_timeout: Duration) -> &mut Self {
+            self.hooks.timeouts.idle_timeout(idle_timeout);
+
+            self
+        }
+
+        /// Limit throughput to `bytes_per_second` on average, and to `burst` bytes at once, sleeping while throttled.
+        pub fn rate_limit(&mut self, bytes_per_second: u64, burst: u64) -> &mut Self {
+            self.hooks.rate = Some(TokenBucket::new(bytes_per_second, burst));
+
+            self
+        }
+
+        /// Total time spent throttled by [`Self::rate_limit`].
+        #[must_use]
+        pub fn throttled(&self) -> Duration {
+            self.hooks.throttled()
+        }
+
+        /// Measure time limits, and sleep while throttled, with `clock` rather than the [`SystemClock`].
+        pub fn clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+            self.hooks.clock = Some(clock);
+
+            self
+        }
+
+        /// Fail with [`ErrorKind::Cancelled`](crate::ErrorKind::Cancelled) once `token` is cancelled.
+        pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+            self.hooks.cancel = Some(token);
+
+            self
+        }
+
+        /// Pass each chunk accepted by the limit to `inspector`, preceded by up to `context_len` bytes of earlier chunks.  A rejection fails with [`ErrorKind::Rejected`](crate::ErrorKind::Rejected).
+        pub fn inspect(
+            &mut self,
+            inspector: impl Inspector + 'static,
+            context_len: usize,
+        ) -> &mut Self {
+            self.hooks.inspection = Some(Inspection::new(inspector, context_len));
+
+            self
+        }
+
+        /// Draw every byte accepted by the limit from `budget` as well, failing with [`ErrorKind::BudgetExceeded`](crate::ErrorKind::BudgetExceeded) once it is exhausted.
+        pub fn shared_budget(&mut self, budget: SharedBudget) -> &mut Self {
+            self.hooks.budget = Some(budget);
+
+            self
+        }
+
+        /// Charge every byte accepted by the limit to `quota` and its ancestors, failing with [`ErrorKind::BudgetExceeded`](crate::ErrorKind::BudgetExceeded) once any of them is exhausted.
+        pub fn quota(&mut self, quota: Quota) -> &mut Self {
+            self.hooks.quota = Some(QuotaCharge::new(quota, false));
+
+            self
+        }
+
+        /// Number of reads issued to the underlying reader.
+        #[must_use]
+        pub fn reader_count(&self) -> usize {
+            self.reader_count
+        }
+
+        pub(crate) fn set_hooks(&mut self, hooks: Hooks) {
+            self.hooks = hooks;
+        }
+
+        pub(crate) fn hooks(&self) -> &Hooks {
+            &self.hooks
+        }
+
+        /// Whether a further byte follows, probing the underlying reader without charging the byte to the hooks.
+        pub(crate) fn has_more(&mut self) -> io::Result<bool> {
+            Ok(!self.buf.is_empty() || self.hooks.probe(&mut self.reader)?)
+        }
+
+        pub(crate) fn into_hooks(self) -> Hooks {
+            self.hooks
+        }
+
+        // NOTE: Reads up to one byte beyond the remaining limit, so that an overshoot is detected rather than silently truncated.
+        fn read_inner(
+            reader: &mut R,
+            limit: &mut u64,
+            reader_count: &mut usize,
+            hooks: &mut Hooks,
+            buf: &mut [u8],
+        ) -> io::Result<usize> {
+            let max_read = saturating_len(limit.saturating_add(1)).min(buf.len());
+
+            let bytes_read = hooks.read(reader, &mut buf[..max_read])?;
+            if bytes_read as u64 > *limit {
+                return Err(io::Error::other(error::TooManyBytes));
+            }
+            *limit -= bytes_read as u64;
+            *reader_count += 1;
+            hooks.accept(&buf[..bytes_read])?;
+
+            Ok(bytes_read)
+        }
     }
 
     impl<R> Read for LimitReaderFallible<R>
     where
         R: Read,
     {
-        #[allow(clippy::cast_possible_truncation)]
-        fn read(&mut self, mut buf: &mut [u8]) -> io::Result<usize> {
-            // NOTE: using +1 in the range below trips the error.
-            buf = &mut buf[..=(self.limit as usize)];
-
-            let bytes_read = self.reader.read(buf)?;
-            if bytes_read > self.limit as usize {
-                return Err(io::Error::new(io::ErrorKind::Other, "too many bytes"));
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            // Bypass the internal buffer entirely if it is empty, so that the hooks are only charged for the bytes the caller asked for.
+            if self.buf.is_empty() {
+                return Self::read_inner(
+                    &mut self.reader,
+                    &mut self.limit,
+                    &mut self.reader_count,
+                    &mut self.hooks,
+                    buf,
+                );
             }
-            self.limit -= bytes_read as u64;
-            self.reader_count += 1;
 
-            Ok(bytes_read)
+            self.fill_buf()?;
+
+            Ok(self.buf.copy_to(buf))
         }
     }
 
@@ -76,10 +419,20 @@ pub(crate) mod falible {
         R: Read,
     {
         fn fill_buf(&mut self) -> io::Result<&[u8]> {
-            unimplemented!("LimitReaderFallible should never call `fill_buf`")
+            let Self {
+                reader,
+                limit,
+                reader_count,
+                buf,
+                hooks,
+            } = self;
+
+            buf.fill(|b| Self::read_inner(reader, limit, reader_count, hooks, b))
         }
 
-        fn consume(&mut self, _: usize) {}
+        fn consume(&mut self, amt: usize) {
+            self.buf.consume(amt);
+        }
     }
 }
 
@@ -95,28 +448,180 @@ pub(crate) mod infalible {
         fn perform_read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
             self.read(buf)
         }
+
+        fn truncated(&self) -> bool {
+            self.truncated()
+        }
     }
 
-    pub(crate) struct LimitReaderInfallible<R>
+    /// A limit adapter that silently truncates the underlying reader after `limit` bytes.
+    ///
+    /// Implements both [`Read`] and [`BufRead`], so methods such as [`BufRead::read_line`], [`BufRead::lines`] and [`BufRead::split`] work directly on the limited stream.  Reads are passed on at the size requested, while [`BufRead::fill_buf`] reads ahead into an internal buffer, charging budgets and quotas for every byte it buffers.
+    pub struct LimitReaderInfallible<R>
     where
         R: Read,
     {
         reader: R,
         limit: u64,
         reader_count: usize,
+        truncated: bool,
+        buf: Buffer,
+        hooks: Hooks,
     }
 
     impl<R> LimitReaderInfallible<R>
     where
         R: Read,
     {
+        /// Wrap `r`, reading at most `limit` bytes from it.
         pub fn new(r: R, limit: u64) -> Self {
             Self {
                 reader: r,
                 limit,
                 reader_count: 0,
+                truncated: false,
+                buf: Buffer::default(),
+                hooks: Hooks::default(),
             }
         }
+
+        /// Mirror every byte accepted by the limit into `writer`, dropping bytes beyond `cap`, or once `writer` fails, without affecting the read.
+        pub fn tee(&mut self, writer: impl Write + Send + 'static, cap: u64) -> &mut Self {
+            self.hooks.tee = Some(Tee::new(writer, cap));
+
+            self
+        }
+
+        /// Bytes mirrored by [`Self::tee`], if set.
+        #[must_use]
+        pub fn tee_stats(&self) -> Option<TeeStats> {
+            self.hooks.tee.as_ref().map(Tee::stats)
+        }
+
+        /// Fail with [`ErrorKind::Timeout`](crate::ErrorKind::Timeout) once reading takes longer than `deadline`, measured from the first read.
+        pub fn deadline(&mut self, deadline: Duration) -> &mut Self {
+            self.hooks.timeouts.deadline(deadline);
+
+            self
+        }
+
+        /// Fail with [`ErrorKind::Timeout`](crate::ErrorKind::Timeout) once the underlying reader yields no bytes for longer than `idle_timeout`.
+        pub fn idle_timeout(&mut self, idle_timeout: Duration) -> &mut Self {
+            self.hooks.timeouts.idle_timeout(idle_timeout);
+
+            self
+        }
+
+        /// Limit throughput to `bytes_per_second` on average, and to `burst` bytes at once, sleeping while throttled.
+        pub fn rate_limit(&mut self, bytes_per_second: u64, burst: u64) -> &mut Self {
+            self.hooks.rate = Some(TokenBucket::new(bytes_per_second, burst));
+
+            self
+        }
+
+        /// Total time spent throttled by [`Self::rate_limit`].
+        #[must_use]
+        pub fn throttled(&self) -> Duration {
+            self.hooks.throttled()
+        }
+
+        /// Measure time limits, and sleep while throttled, with `clock` rather than the [`SystemClock`].
+        pub fn clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+            self.hooks.clock = Some(clock);
+
+            self
+        }
+
+        /// Fail with [`ErrorKind::Cancelled`](crate::ErrorKind::Cancelled) once `token` is cancelled.
+        pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+            self.hooks.cancel = Some(token);
+
+            self
+        }
+
+        /// Pass each chunk accepted by the limit to `inspector`, preceded by up to `context_len` bytes of earlier chunks.  A rejection fails with [`ErrorKind::Rejected`](crate::ErrorKind::Rejected).
+        pub fn inspect(
+            &mut self,
+            inspector: impl Inspector + 'static,
+            context_len: usize,
+        ) -> &mut Self {
+            self.hooks.inspection = Some(Inspection::new(inspector, context_len));
+
+            self
+        }
+
+        /// Draw every byte accepted by the limit from `budget` as well, failing with [`ErrorKind::BudgetExceeded`](crate::ErrorKind::BudgetExceeded) once it is exhausted.
+        pub fn shared_budget(&mut self, budget: SharedBudget) -> &mut Self {
+            self.hooks.budget = Some(budget);
+
+            self
+        }
+
+        /// Charge every byte accepted by the limit to `quota` and its ancestors, truncating the stream once any of them is exhausted.  Each read is charged for the bytes it returns, rather than reserving quota it may not use.
+        pub fn quota(&mut self, quota: Quota) -> &mut Self {
+            self.hooks.quota = Some(QuotaCharge::new(quota, true));
+
+            self
+        }
+
+        /// Number of reads issued to the underlying reader.
+        #[must_use]
+        pub fn reader_count(&self) -> usize {
+            self.reader_count
+        }
+
+        pub(crate) fn set_hooks(&mut self, hooks: Hooks) {
+            self.hooks = hooks;
+        }
+
+        pub(crate) fn hooks(&self) -> &Hooks {
+            &self.hooks
+        }
+
+        /// Whether a further byte follows, probing the underlying reader without charging the byte to the hooks.
+        pub(crate) fn has_more(&mut self) -> io::Result<bool> {
+            Ok(!self.buf.is_empty() || self.hooks.probe(&mut self.reader)?)
+        }
+
+        pub(crate) fn into_hooks(self) -> Hooks {
+            self.hooks
+        }
+
+        /// Whether the underlying reader held more than `limit` bytes.
+        ///
+        /// This is only known once the limit has been reached and a further read was attempted, e.g. by reading to the end.
+        #[must_use]
+        pub fn truncated(&self) -> bool {
+            self.truncated || self.hooks.truncated()
+        }
+
+        // NOTE: Once the limit is reached, probes the underlying reader for a single byte (which is discarded) to find out whether the stream was truncated.
+        fn read_inner(
+            reader: &mut R,
+            limit: &mut u64,
+            reader_count: &mut usize,
+            truncated: &mut bool,
+            hooks: &mut Hooks,
+            buf: &mut [u8],
+        ) -> io::Result<usize> {
+            if *limit == 0 && !buf.is_empty() {
+                if !*truncated {
+                    *truncated = hooks.probe(reader)?;
+                }
+                return Ok(0);
+            }
+            let max_read = saturating_len(*limit).min(buf.len()); // min of limit and buf.len()
+            if max_read == 0 {
+                return Ok(0);
+            }
+
+            let bytes_read = hooks.read(reader, &mut buf[..max_read])?;
+            *limit -= bytes_read as u64;
+            *reader_count += 1;
+            hooks.accept(&buf[..bytes_read])?;
+
+            Ok(bytes_read)
+        }
     }
 
     impl<R> Read for LimitReaderInfallible<R>
@@ -124,22 +629,21 @@ pub(crate) mod infalible {
         R: Read,
     {
         fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-            match TryInto::<u64>::try_into(buf.len()) {
-                Ok(buf_len) => {
-                    let max_read = self.limit.min(buf_len); // min of limit and buf.len()
-
-                    match TryInto::<usize>::try_into(max_read) {
-                        Ok(m) => {
-                            let bytes_read = self.reader.read(&mut buf[..m])?;
-                            self.reader_count += 1;
-
-                            Ok(bytes_read)
-                        }
-                        Err(_) => Ok(0),
-                    }
-                }
-                Err(_) => Ok(0),
+            // Bypass the internal buffer entirely if it is empty, so that the hooks are only charged for the bytes the caller asked for.
+            if self.buf.is_empty() {
+                return Self::read_inner(
+                    &mut self.reader,
+                    &mut self.limit,
+                    &mut self.reader_count,
+                    &mut self.truncated,
+                    &mut self.hooks,
+                    buf,
+                );
             }
+
+            self.fill_buf()?;
+
+            Ok(self.buf.copy_to(buf))
         }
     }
 
@@ -148,9 +652,91 @@ pub(crate) mod infalible {
         R: Read,
     {
         fn fill_buf(&mut self) -> io::Result<&[u8]> {
-            unreachable!("LimitReaderInfallible should never call `fill_buf`")
+            let Self {
+                reader,
+                limit,
+                reader_count,
+                truncated,
+                buf,
+                hooks,
+            } = self;
+
+            buf.fill(|b| Self::read_inner(reader, limit, reader_count, truncated, hooks, b))
+        }
+
+        fn consume(&mut self, amt: usize) {
+            self.buf.consume(amt);
         }
+    }
+}
 
-        fn consume(&mut self, _amt: usize) {}
+/// Either limit adapter, selected at runtime by [`crate::LimitReader`].
+pub(crate) enum Adapter<R: Read> {
+    Fallible(LimitReaderFallible<R>),
+    Infallible(LimitReaderInfallible<R>),
+}
+
+impl<R: Read> Adapter<R> {
+    /// Wrap `r` in a [`LimitReaderFallible`] if `strict`, otherwise in a [`LimitReaderInfallible`].
+    pub(crate) fn new(r: R, limit: u64, strict: bool, hooks: Hooks) -> Self {
+        if strict {
+            let mut reader = LimitReaderFallible::new(r, limit);
+            reader.set_hooks(hooks);
+            Adapter::Fallible(reader)
+        } else {
+            let mut reader = LimitReaderInfallible::new(r, limit);
+            reader.set_hooks(hooks);
+            Adapter::Infallible(reader)
+        }
+    }
+
+    pub(crate) fn reader_count(&self) -> usize {
+        match self {
+            Adapter::Fallible(reader) => reader.reader_count(),
+            Adapter::Infallible(reader) => reader.reader_count(),
+        }
+    }
+
+    pub(crate) fn hooks(&self) -> &Hooks {
+        match self {
+            Adapter::Fallible(reader) => reader.hooks(),
+            Adapter::Infallible(reader) => reader.hooks(),
+        }
+    }
+
+    pub(crate) fn into_hooks(self) -> Hooks {
+        match self {
+            Adapter::Fallible(reader) => reader.into_hooks(),
+            Adapter::Infallible(reader) => reader.into_hooks(),
+        }
+    }
+
+    /// Whether the source was truncated by the limit, or by the reader's buffer being full.  A full buffer is probed for a further byte, which fails a strict adapter as exceeding the limit would.
+    pub(crate) fn truncated(&mut self, buffer_full: bool) -> io::Result<bool> {
+        match self {
+            Adapter::Fallible(reader) => {
+                if buffer_full && reader.has_more()? {
+                    return Err(io::Error::other(error::TooManyBytes));
+                }
+
+                Ok(false)
+            }
+            Adapter::Infallible(reader) => {
+                if Readable::truncated(reader) {
+                    return Ok(true);
+                }
+
+                Ok(buffer_full && reader.has_more()?)
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for Adapter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Adapter::Fallible(reader) => reader.perform_read(buf),
+            Adapter::Infallible(reader) => reader.perform_read(buf),
+        }
     }
 }
@@ -0,0 +1,428 @@
+use crate::encoding::Encoding;
+use crate::error::{ErrorKind, LimitReaderError, RatioExceededError};
+use crate::{LimitReader, LimitReaderResult, Sink};
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Sweeps a directory tree, running each file through a [`LimitReader`] with auto-detected decoding, e.g. to find decompression bombs among uploads mirrored to local disk.
+///
+/// Symbolic links are not followed.  Entries are visited in order of their names, so that sweeps of the same tree are comparable.
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    limit: u64,
+    max_ratio: Option<f64>,
+    max_files: usize,
+    max_depth: usize,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scanner {
+    /// Default cap on the decoded bytes read from each file.
+    pub const DEFAULT_LIMIT: u64 = 16 * 1024 * 1024;
+
+    /// Default maximum number of files scanned.
+    pub const DEFAULT_MAX_FILES: usize = 10_000;
+
+    /// Default maximum depth of the files scanned.
+    pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+    /// Create a [`Scanner`] with the default limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            limit: Self::DEFAULT_LIMIT,
+            max_ratio: None,
+            max_files: Self::DEFAULT_MAX_FILES,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Stop decoding each file after `limit` bytes, marking it as [`Verdict::Truncated`].
+    pub fn limit(&mut self, limit: u64) -> &mut Self {
+        self.limit = limit;
+
+        self
+    }
+
+    /// Reject files whose decoded bytes exceed `ratio` times their compressed bytes; see [`LimitReader::max_ratio`].
+    pub fn max_ratio(&mut self, ratio: f64) -> &mut Self {
+        self.max_ratio = Some(ratio);
+
+        self
+    }
+
+    /// Scan at most `max_files` files.
+    pub fn max_files(&mut self, max_files: usize) -> &mut Self {
+        self.max_files = max_files;
+
+        self
+    }
+
+    /// Skip files more than `max_depth` directories below the root; files directly within the root are at depth 1.
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = max_depth;
+
+        self
+    }
+
+    /// Scan the files below `root`.  Files that cannot be read are reported as [`Verdict::Rejected`], rather than failing the sweep.
+    ///
+    /// # Errors
+    ///
+    /// If `root` cannot be read as a directory, this returns a [`LimitReaderError`] of the kind [`ErrorKind::IoError`].
+    ///
+    pub fn scan(&self, root: impl AsRef<Path>) -> LimitReaderResult<ScanReport> {
+        let mut report = ScanReport {
+            files: Vec::new(),
+            complete: true,
+        };
+        let entries = sorted_entries(root.as_ref())?;
+        self.scan_entries(entries, 1, &mut report);
+
+        Ok(report)
+    }
+
+    fn scan_entries(&self, entries: Vec<PathBuf>, depth: usize, report: &mut ScanReport) {
+        for path in entries {
+            let file_type = match fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata.file_type(),
+                Err(err) => {
+                    report.files.push(FileReport::unreadable(path, &err.into()));
+                    continue;
+                }
+            };
+
+            if file_type.is_dir() {
+                if depth >= self.max_depth {
+                    report.complete = false;
+                    continue;
+                }
+                match sorted_entries(&path) {
+                    Ok(entries) => self.scan_entries(entries, depth + 1, report),
+                    Err(err) => report.files.push(FileReport::unreadable(path, &err)),
+                }
+            } else if file_type.is_file() {
+                if report.files.len() >= self.max_files {
+                    report.complete = false;
+                    return;
+                }
+                let file_report = match self.scan_file(&path) {
+                    Ok(file_report) => file_report,
+                    Err(err) => FileReport::unreadable(path, &err),
+                };
+                report.files.push(file_report);
+            }
+        }
+    }
+
+    fn scan_file(&self, path: &Path) -> LimitReaderResult<FileReport> {
+        let f = File::open(path)?;
+        let source_size = f.metadata()?.len();
+        let mut f = BufReader::new(f);
+        let encoding = Encoding::detect(f.fill_buf()?);
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader.limit(self.limit).enable_decode_auto();
+        if let Some(ratio) = self.max_ratio {
+            limit_reader.max_ratio(ratio);
+        }
+        let (decoded_size, verdict, rejection) = match limit_reader.read_source(
+            f,
+            Some(source_size),
+            false,
+            Sink::Writer(&mut io::sink()),
+        ) {
+            Ok(outcome) if outcome.truncated => {
+                (outcome.bytes_read as u64, Verdict::Truncated, None)
+            }
+            Ok(outcome) => (outcome.bytes_read as u64, Verdict::Ok, None),
+            Err(err) => {
+                let decoded_size = err
+                    .get_ref()
+                    .downcast_ref::<RatioExceededError>()
+                    .map_or(0, RatioExceededError::decoded_bytes);
+                (decoded_size, Verdict::Rejected, Some(Rejection::from(&err)))
+            }
+        };
+
+        Ok(FileReport {
+            path: path.to_path_buf(),
+            source_size,
+            decoded_size,
+            encoding,
+            verdict,
+            rejection,
+        })
+    }
+}
+
+fn sorted_entries(dir: &Path) -> LimitReaderResult<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    Ok(entries)
+}
+
+/// Outcome of a [`Scanner::scan`].
+#[derive(Debug, Clone)]
+pub struct ScanReport {
+    files: Vec<FileReport>,
+    complete: bool,
+}
+
+impl ScanReport {
+    /// Reports of the files scanned.
+    #[must_use]
+    pub fn files(&self) -> &[FileReport] {
+        &self.files
+    }
+
+    /// Files that were truncated or rejected.
+    pub fn flagged(&self) -> impl Iterator<Item = &FileReport> {
+        self.files
+            .iter()
+            .filter(|file| file.verdict() != Verdict::Ok)
+    }
+
+    /// Whether every file in the tree was scanned, i.e. neither [`Scanner::max_files`] nor [`Scanner::max_depth`] cut the sweep short.
+    #[must_use]
+    pub fn complete(&self) -> bool {
+        self.complete
+    }
+}
+
+/// Report of a single file scanned by a [`Scanner`].
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    path: PathBuf,
+    source_size: u64,
+    decoded_size: u64,
+    encoding: Encoding,
+    verdict: Verdict,
+    rejection: Option<Rejection>,
+}
+
+#[derive(Debug, Clone)]
+struct Rejection {
+    kind: ErrorKind,
+    reason: String,
+}
+
+impl From<&LimitReaderError> for Rejection {
+    fn from(err: &LimitReaderError) -> Self {
+        Self {
+            kind: err.kind(),
+            reason: err.get_ref().to_string(),
+        }
+    }
+}
+
+impl FileReport {
+    fn unreadable(path: PathBuf, err: &LimitReaderError) -> Self {
+        Self {
+            path,
+            source_size: 0,
+            decoded_size: 0,
+            encoding: Encoding::Identity,
+            verdict: Verdict::Rejected,
+            rejection: Some(Rejection::from(err)),
+        }
+    }
+
+    /// Path of the file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the file on disk.
+    #[must_use]
+    pub fn source_size(&self) -> u64 {
+        self.source_size
+    }
+
+    /// Bytes decoded from the file, capped by [`Scanner::limit`].
+    #[must_use]
+    pub fn decoded_size(&self) -> u64 {
+        self.decoded_size
+    }
+
+    /// Ratio of the decoded to the source size.  As the decoded size is capped, so is the ratio of truncated files.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn ratio(&self) -> f64 {
+        self.decoded_size as f64 / self.source_size.max(1) as f64
+    }
+
+    /// Encoding detected from the first bytes of the file.
+    #[must_use]
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Verdict on the file.
+    #[must_use]
+    pub fn verdict(&self) -> Verdict {
+        self.verdict
+    }
+
+    /// Kind of the error a rejected file failed with.
+    #[must_use]
+    pub fn error_kind(&self) -> Option<ErrorKind> {
+        self.rejection.as_ref().map(|rejection| rejection.kind)
+    }
+
+    /// Why the file was rejected.
+    #[must_use]
+    pub fn reason(&self) -> Option<&str> {
+        self.rejection
+            .as_ref()
+            .map(|rejection| rejection.reason.as_str())
+    }
+}
+
+/// Verdict of a [`Scanner`] on a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Verdict {
+    /// The file was decoded in full, within the limits.
+    Ok,
+    /// Decoding stopped at [`Scanner::limit`].
+    Truncated,
+    /// The file could not be read or decoded, or exceeded [`Scanner::max_ratio`].
+    Rejected,
+}
+
+impl Verdict {
+    /// The lowercase name of the verdict, e.g. `truncated`.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Truncated => "truncated",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut e = GzEncoder::new(Vec::new(), Compression::best());
+        e.write_all(data).unwrap();
+        e.finish().unwrap()
+    }
+
+    #[test]
+    fn flags_bombs_and_truncated_files() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("uploads/nested")).unwrap();
+        fs::write(dir.path().join("a.txt"), "Mike was here. Briefly.").unwrap();
+        fs::write(
+            dir.path().join("uploads/b.gz"),
+            compress(b"Mike was here. Briefly."),
+        )
+        .unwrap();
+        fs::write(dir.path().join("uploads/c.gz"), compress(&[0; 1 << 20])).unwrap();
+        // Incompressible, so that it is truncated before exceeding the ratio.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let noise: Vec<u8> = (0..100_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect();
+        let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
+        e.write_all(&noise).unwrap();
+        fs::write(dir.path().join("uploads/nested/d.z"), e.finish().unwrap()).unwrap();
+
+        let report = Scanner::new()
+            .limit(64 * 1024)
+            .max_ratio(10.0)
+            .scan(dir.path())
+            .unwrap();
+        assert!(report.complete());
+        let verdicts: Vec<_> = report
+            .files()
+            .iter()
+            .map(|file| {
+                let name = file.path().strip_prefix(dir.path()).unwrap();
+                (
+                    name.to_str().unwrap().to_string(),
+                    file.encoding(),
+                    file.verdict(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            verdicts,
+            [
+                ("a.txt".to_string(), Encoding::Identity, Verdict::Ok),
+                ("uploads/b.gz".to_string(), Encoding::Gzip, Verdict::Ok),
+                (
+                    "uploads/c.gz".to_string(),
+                    Encoding::Gzip,
+                    Verdict::Rejected
+                ),
+                (
+                    "uploads/nested/d.z".to_string(),
+                    Encoding::Zlib,
+                    Verdict::Truncated
+                ),
+            ]
+        );
+
+        let bomb = &report.files()[2];
+        assert_eq!(bomb.error_kind(), Some(ErrorKind::RatioExceeded));
+        assert!(bomb.ratio() > 10.0);
+        assert_eq!(report.files()[3].decoded_size(), 64 * 1024);
+        assert_eq!(report.flagged().count(), 2);
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn bounds_file_count_and_depth() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("1.txt"), "one").unwrap();
+        fs::write(dir.path().join("a/2.txt"), "two").unwrap();
+        fs::write(dir.path().join("a/b/3.txt"), "three").unwrap();
+        fs::write(dir.path().join("z.txt"), "last").unwrap();
+
+        let report = Scanner::new().max_depth(2).scan(dir.path()).unwrap();
+        assert!(!report.complete());
+        assert_eq!(report.files().len(), 3);
+
+        let report = Scanner::new().max_files(2).scan(dir.path()).unwrap();
+        assert!(!report.complete());
+        assert_eq!(report.files().len(), 2);
+
+        assert!(Scanner::new().scan(dir.path().join("missing")).is_err());
+
+        dir.close().unwrap();
+    }
+}
@@ -0,0 +1,21 @@
+use std::fmt::{self, Display, Formatter};
+
+/// Which bytes of a read a digest or tee covers, as configured by [`crate::LimitReader::digest`] and [`crate::LimitReader::tee`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Stage {
+    /// The raw bytes of the source, before decoding.
+    Raw,
+    /// The decoded bytes accepted by the limit, i.e. those returned by the read.  A truncating read covers only the bytes it kept.
+    Decoded,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Raw => f.write_str("raw"),
+            Stage::Decoded => f.write_str("decoded"),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::{self, Read, Write};
+
+/// Bytes mirrored into, and dropped from, a tee writer.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TeeStats {
+    written: u64,
+    dropped: u64,
+    error: Option<String>,
+}
+
+impl TeeStats {
+    /// Bytes written to the tee writer.
+    #[must_use]
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Bytes not written to the tee writer because they exceeded its cap, or because it failed.
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// The error the tee writer failed with, if any.  Once it fails, the bytes still to mirror are dropped without affecting the read.
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Mirrors bytes into a writer, up to a cap.
+pub(crate) struct Tee {
+    writer: Box<dyn Write + Send>,
+    cap: u64,
+    stats: TeeStats,
+}
+
+impl Tee {
+    pub(crate) fn new(writer: impl Write + Send + 'static, cap: u64) -> Self {
+        Self {
+            writer: Box::new(writer),
+            cap,
+            stats: TeeStats::default(),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> TeeStats {
+        self.stats.clone()
+    }
+
+    /// Start counting towards the cap afresh.
+    pub(crate) fn reset(&mut self) {
+        self.stats = TeeStats::default();
+    }
+
+    /// Write as much of `data` as the cap allows, dropping the rest.  An error of the writer is recorded rather than returned, and drops all further bytes.
+    pub(crate) fn mirror(&mut self, data: &[u8]) {
+        let remaining = self.cap - self.stats.written;
+        let mut len = usize::try_from(remaining).map_or(data.len(), |r| r.min(data.len()));
+        if self.stats.error.is_some() {
+            len = 0;
+        } else if let Err(err) = self.writer.write_all(&data[..len]) {
+            self.stats.error = Some(err.to_string());
+            len = 0;
+        }
+        self.stats.written += len as u64;
+        self.stats.dropped += (data.len() - len) as u64;
+    }
+}
+
+/// Mirrors all bytes read through it into a [`Tee`], if any.
+pub(crate) struct TeeReader<'a, R> {
+    inner: R,
+    tee: Option<&'a mut Tee>,
+}
+
+impl<'a, R: Read> TeeReader<'a, R> {
+    pub(crate) fn new(inner: R, tee: Option<&'a mut Tee>) -> Self {
+        Self { inner, tee }
+    }
+}
+
+impl<R: Read> Read for TeeReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(tee) = &mut self.tee {
+            tee.mirror(&buf[..n]);
+        }
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LimitReader, LimitReaderInfallible, Stage};
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::fs::File;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuf {
+        fn contents(&self) -> Vec<u8> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn adapter_tee_drops_bytes_beyond_cap() {
+        let audit = SharedBuf::default();
+        let mut reader = LimitReaderInfallible::new("Mike was here. Briefly.".as_bytes(), 14);
+        reader.tee(audit.clone(), 4);
+
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "Mike was here.");
+        assert_eq!(audit.contents(), b"Mike");
+
+        let stats = reader.tee_stats().unwrap();
+        assert_eq!(stats.written(), 4);
+        assert_eq!(stats.dropped(), 10);
+    }
+
+    #[test]
+    fn tees_raw_or_decoded_bytes() {
+        let dir = tempdir().unwrap();
+
+        let text = "Mike was here. Briefly.";
+        let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
+        e.write_all(text.as_bytes()).unwrap();
+        let compressed = e.finish().unwrap();
+
+        let file_path = dir.path().join("test_output.txt.z");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(&compressed).unwrap();
+
+        let decoded = SharedBuf::default();
+        let mut limit_reader = LimitReader::new();
+        limit_reader
+            .enable_decode_zlib()
+            .tee(decoded.clone(), Stage::Decoded, 1024);
+        let output = limit_reader.read_limited(file_path.clone()).unwrap();
+        assert_eq!(decoded.contents(), text.as_bytes());
+        assert_eq!(output.tee_stats().unwrap().written(), text.len() as u64);
+
+        let raw = SharedBuf::default();
+        limit_reader.tee(raw.clone(), Stage::Raw, 1024);
+        limit_reader.read(file_path).unwrap();
+        assert_eq!(raw.contents(), compressed);
+        assert_eq!(limit_reader.tee_stats().unwrap().dropped(), 0);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failing_tee_does_not_fail_read() {
+        let mut reader = LimitReaderInfallible::new("Mike was here. Briefly.".as_bytes(), 14);
+        reader.tee(Broken, 1024);
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "Mike was here.");
+        let stats = reader.tee_stats().unwrap();
+        assert_eq!((stats.written(), stats.dropped()), (0, 14));
+        assert_eq!(stats.error(), Some("disk full"));
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader.tee(Broken, Stage::Raw, 1024);
+        let output = limit_reader
+            .copy(&b"Mike was here. Briefly."[..], &mut Vec::new())
+            .unwrap();
+        assert_eq!(output.bytes_read(), 23);
+        assert_eq!(output.tee_stats().unwrap().error(), Some("disk full"));
+    }
+}
@@ -0,0 +1,162 @@
+/// Where [`crate::LimitReader::read_limited_text`] cuts truncated text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TextBoundary {
+    /// Cut at the last complete UTF-8 character.
+    #[default]
+    Char,
+    /// Cut at the start of the last grapheme cluster, as it may be incomplete.
+    #[cfg(feature = "grapheme")]
+    Grapheme,
+    /// Cut after the last newline.
+    Line,
+}
+
+/// Number of trailing bytes of `bytes` forming an incomplete UTF-8 sequence, e.g. a multi-byte character cut short by a limit.
+fn incomplete_tail_len(bytes: &[u8]) -> usize {
+    // A UTF-8 sequence is at most 4 bytes long, so only the last 3 bytes can belong to an incomplete one.
+    for len in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - len];
+        if byte & 0b1100_0000 == 0b1000_0000 {
+            // Continuation byte; keep looking for the leading byte.
+            continue;
+        }
+        let width = match byte {
+            0b1100_0000..=0b1101_1111 => 2,
+            0b1110_0000..=0b1110_1111 => 3,
+            0b1111_0000..=0b1111_0111 => 4,
+            _ => return 0,
+        };
+
+        return if width > len { len } else { 0 };
+    }
+
+    0
+}
+
+/// Length of the prefix of `bytes` that ends on `boundary`.
+///
+/// Alignment only applies when the input was `truncated` by a limit; complete input is returned as-is.
+pub(crate) fn aligned_len(bytes: &[u8], boundary: TextBoundary, truncated: bool) -> usize {
+    if !truncated {
+        return bytes.len();
+    }
+    let len = bytes.len() - incomplete_tail_len(bytes);
+
+    match boundary {
+        TextBoundary::Char => len,
+        #[cfg(feature = "grapheme")]
+        TextBoundary::Grapheme => {
+            use unicode_segmentation::UnicodeSegmentation;
+
+            // Invalid UTF-8 is left to the caller, aligned to the last complete character only.
+            match std::str::from_utf8(&bytes[..len]) {
+                Ok(text) => text
+                    .grapheme_indices(true)
+                    .next_back()
+                    .map_or(0, |(i, _)| i),
+                Err(_) => len,
+            }
+        }
+        TextBoundary::Line => bytes[..len]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, LimitReader};
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn finds_incomplete_tail() {
+        let text = "a€".as_bytes(); // '€' is 3 bytes long
+        assert_eq!(incomplete_tail_len(text), 0);
+        assert_eq!(incomplete_tail_len(&text[..3]), 2);
+        assert_eq!(incomplete_tail_len(&text[..2]), 1);
+        assert_eq!(incomplete_tail_len(b"abc"), 0);
+    }
+
+    #[test]
+    fn aligns_to_boundaries() {
+        let text = "one\ntwo €".as_bytes();
+        let cut = &text[..text.len() - 1];
+
+        assert_eq!(aligned_len(cut, TextBoundary::Char, true), cut.len() - 2);
+        assert_eq!(aligned_len(cut, TextBoundary::Line, true), 4);
+        assert_eq!(aligned_len(text, TextBoundary::Line, false), text.len());
+    }
+
+    #[cfg(feature = "grapheme")]
+    #[test]
+    fn aligns_to_grapheme_boundary() {
+        // 'e' followed by a combining acute accent forms a single grapheme cluster.
+        let text = "cafe\u{301}".as_bytes();
+
+        assert_eq!(aligned_len(text, TextBoundary::Grapheme, true), 3);
+    }
+
+    #[test]
+    fn read_limited_text_drops_partial_character() {
+        let dir = tempdir().unwrap();
+
+        let file_path = dir.path().join("test_output.txt");
+        let mut file = File::create(&file_path).unwrap();
+        write!(file, "Mike was €€ here.").unwrap();
+
+        // The limit cuts the second '€' after its first byte.
+        let mut limit_reader = LimitReader::new();
+        limit_reader.limit(13);
+
+        let (text, output) = limit_reader
+            .read_limited_text(file_path.clone(), TextBoundary::Char)
+            .unwrap();
+        assert_eq!(text, "Mike was €");
+        assert_eq!(output.bytes_read(), 13);
+        assert_eq!(output.boundary_bytes_dropped(), 1);
+
+        let err = limit_reader
+            .read_limited(file_path.clone())
+            .and_then(|output| {
+                Ok(String::from_utf8(
+                    limit_reader.buffer()[..output.bytes_read() as usize].to_vec(),
+                )?)
+            });
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::Utf8Error);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn read_limited_text_lossy_replaces_invalid_bytes() {
+        let dir = tempdir().unwrap();
+
+        let file_path = dir.path().join("test_output.txt");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(b"Mike\xff was\nhere \xe2\x82\xac").unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader.limit(16);
+
+        let (text, output) = limit_reader
+            .read_limited_text_lossy(file_path.clone(), TextBoundary::Char)
+            .unwrap();
+        assert_eq!(text, "Mike\u{FFFD} was\nhere ");
+        assert_eq!(output.boundary_bytes_dropped(), 1);
+
+        let (text, output) = limit_reader
+            .read_limited_text_lossy(file_path, TextBoundary::Line)
+            .unwrap();
+        assert_eq!(text, "Mike\u{FFFD} was\n");
+        assert_eq!(output.boundary_bytes_dropped(), 6);
+
+        drop(file);
+        dir.close().unwrap();
+    }
+}
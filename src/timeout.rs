@@ -0,0 +1,214 @@
+use crate::clock::Clock;
+use crate::error::{ErrorKind, LimitReaderError};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Which time limit a read exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// The whole read took longer than its deadline.
+    Deadline,
+    /// The source made no progress for longer than the idle timeout.
+    Idle,
+}
+
+/// Structured error for [`ErrorKind::Timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutError {
+    kind: TimeoutKind,
+    limit: Duration,
+}
+
+impl TimeoutError {
+    /// Which time limit was exceeded.
+    #[must_use]
+    pub fn kind(&self) -> TimeoutKind {
+        self.kind
+    }
+
+    /// The configured duration of the time limit.
+    #[must_use]
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+}
+
+impl Display for TimeoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TimeoutKind::Deadline => write!(f, "deadline of {:?} exceeded", self.limit),
+            TimeoutKind::Idle => write!(f, "no progress for more than {:?}", self.limit),
+        }
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+/// Deadline and idle timeout of a read, measured from its first call to the underlying reader.
+///
+/// A blocking read cannot be interrupted, so limits are checked whenever the underlying reader returns.
+#[derive(Clone, Default)]
+pub(crate) struct Timeouts {
+    deadline: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    started: Option<Instant>,
+    last_progress: Option<Instant>,
+}
+
+impl Timeouts {
+    pub(crate) fn deadline(&mut self, deadline: Duration) {
+        self.deadline = Some(deadline);
+    }
+
+    pub(crate) fn idle_timeout(&mut self, idle_timeout: Duration) {
+        self.idle_timeout = Some(idle_timeout);
+    }
+
+    fn is_set(&self) -> bool {
+        self.deadline.is_some() || self.idle_timeout.is_some()
+    }
+
+    /// Start the timers on the first call, and check them on every further call.
+    pub(crate) fn before_read(&mut self, clock: &dyn Clock) -> io::Result<()> {
+        if !self.is_set() {
+            return Ok(());
+        }
+        let now = clock.now();
+        self.started.get_or_insert(now);
+        self.last_progress.get_or_insert(now);
+
+        self.check(now)
+    }
+
+    /// Check the timers once the underlying reader returned `bytes_read` bytes.
+    pub(crate) fn after_read(&mut self, clock: &dyn Clock, bytes_read: usize) -> io::Result<()> {
+        if !self.is_set() {
+            return Ok(());
+        }
+        let now = clock.now();
+        self.check(now)?;
+        if bytes_read > 0 {
+            self.last_progress = Some(now);
+        }
+
+        Ok(())
+    }
+
+    fn check(&self, now: Instant) -> io::Result<()> {
+        let exceeded = |kind, limit: Option<Duration>, since: Option<Instant>| match (limit, since)
+        {
+            (Some(limit), Some(since)) if now.duration_since(since) > limit => {
+                Err(LimitReaderError::new(ErrorKind::Timeout, TimeoutError { kind, limit }).into())
+            }
+            _ => Ok(()),
+        };
+
+        exceeded(TimeoutKind::Deadline, self.deadline, self.started)?;
+        exceeded(TimeoutKind::Idle, self.idle_timeout, self.last_progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LimitReader, LimitReaderFallible, LimitReaderInfallible, ManualClock};
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    // Yields one byte per read, advancing the clock by `delay` each time.
+    struct Trickle {
+        clock: Arc<ManualClock>,
+        delay: Duration,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.clock.advance(self.delay);
+            buf[0] = b'x';
+
+            Ok(1)
+        }
+    }
+
+    fn trickle(delay: Duration) -> (Arc<ManualClock>, Trickle) {
+        let clock = Arc::new(ManualClock::new());
+        let reader = Trickle {
+            clock: Arc::clone(&clock),
+            delay,
+        };
+
+        (clock, reader)
+    }
+
+    fn timeout_kind(err: &io::Error) -> TimeoutKind {
+        err.get_ref()
+            .and_then(|err| err.downcast_ref::<LimitReaderError>())
+            .and_then(|err| err.get_ref().downcast_ref::<TimeoutError>())
+            .map(TimeoutError::kind)
+            .unwrap()
+    }
+
+    #[test]
+    fn idle_timeout_fails_slow_source() {
+        let (clock, source) = trickle(Duration::from_secs(2));
+        let mut reader = LimitReaderInfallible::new(source, 100);
+        reader.idle_timeout(Duration::from_secs(1)).clock(clock);
+
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(timeout_kind(&err), TimeoutKind::Idle);
+    }
+
+    #[test]
+    fn deadline_fails_trickling_source() {
+        let (clock, source) = trickle(Duration::from_secs(1));
+        let mut reader = LimitReaderFallible::new(source, 100);
+        reader
+            .idle_timeout(Duration::from_secs(5))
+            .deadline(Duration::from_secs(3))
+            .clock(clock);
+
+        let mut buf = [0; 1];
+        for _ in 0..3 {
+            reader.read_exact(&mut buf).unwrap();
+        }
+        let err = reader.read_exact(&mut buf).unwrap_err();
+        assert_eq!(timeout_kind(&err), TimeoutKind::Deadline);
+    }
+
+    // Advances by a second whenever it is read.
+    struct Ticking(ManualClock);
+
+    impl Clock for Ticking {
+        fn now(&self) -> Instant {
+            self.0.advance(Duration::from_secs(1));
+            self.0.now()
+        }
+    }
+
+    #[test]
+    fn limit_reader_deadline() {
+        let dir = tempdir().unwrap();
+
+        let file_path = dir.path().join("test_output.txt");
+        let mut file = File::create(&file_path).unwrap();
+        write!(file, "Mike was here. Briefly.").unwrap();
+
+        let mut limit_reader = LimitReader::new();
+        limit_reader.clock(Arc::new(Ticking(ManualClock::new())));
+        assert!(limit_reader.read(file_path.clone()).is_ok());
+
+        limit_reader.deadline(Duration::from_millis(500));
+        let err = limit_reader.read(file_path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+        assert_eq!(
+            err.get_ref().to_string(),
+            "deadline of 500ms exceeded".to_string()
+        );
+
+        drop(file);
+        dir.close().unwrap();
+    }
+}
@@ -0,0 +1,858 @@
+use crate::error::{ErrorKind, LimitReaderError};
+use crate::{LimitReader, LimitReaderFallible, LimitReaderResult as Result};
+use quick_xml::events::attributes::Attribute;
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::Reader;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::Arc;
+
+/// Limits enforced on an XML document by an [`XmlReader`].
+///
+/// Entity expansion is limited to 10,000 references and 1 MiB of replacement text by default, as a few hundred bytes of nested entities can otherwise expand into gigabytes.  The structural limits are disabled by default, and external entities are allowed, though never resolved.
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_field_names)]
+pub struct XmlLimits {
+    max_entity_expansions: u64,
+    max_entity_bytes: u64,
+    max_depth: Option<usize>,
+    max_attributes: Option<usize>,
+    max_text_len: Option<usize>,
+    deny_external_entities: bool,
+}
+
+impl Default for XmlLimits {
+    fn default() -> Self {
+        Self {
+            max_entity_expansions: 10_000,
+            max_entity_bytes: 1024 * 1024,
+            max_depth: None,
+            max_attributes: None,
+            max_text_len: None,
+            deny_external_entities: false,
+        }
+    }
+}
+
+impl XmlLimits {
+    /// Create a new [`XmlLimits`] with the default limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the document to `max_entity_expansions` references to declared entities in total, counting references nested in the replacement text of an entity each time it is expanded.
+    pub fn max_entity_expansions(&mut self, max_entity_expansions: u64) -> &mut Self {
+        self.max_entity_expansions = max_entity_expansions;
+
+        self
+    }
+
+    /// Limit the replacement text produced by expanding entities to `max_entity_bytes` bytes in total.
+    pub fn max_entity_bytes(&mut self, max_entity_bytes: u64) -> &mut Self {
+        self.max_entity_bytes = max_entity_bytes;
+
+        self
+    }
+
+    /// Limit the nesting of elements to `max_depth` levels; the root element is at depth 1.
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = Some(max_depth);
+
+        self
+    }
+
+    /// Limit each element to `max_attributes` attributes.
+    pub fn max_attributes(&mut self, max_attributes: usize) -> &mut Self {
+        self.max_attributes = Some(max_attributes);
+
+        self
+    }
+
+    /// Limit each text node, including CDATA sections and expanded entities, to `max_text_len` bytes as encoded in the document.
+    pub fn max_text_len(&mut self, max_text_len: usize) -> &mut Self {
+        self.max_text_len = Some(max_text_len);
+
+        self
+    }
+
+    /// Reject documents referring to an external DTD or declaring external entities, rather than leaving them unresolved.
+    pub fn deny_external_entities(&mut self) -> &mut Self {
+        self.deny_external_entities = true;
+
+        self
+    }
+}
+
+/// The limit violated by an XML document; see [`XmlLimitError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum XmlViolation {
+    /// The document referred to declared entities more than `max_entity_expansions` times.
+    TooManyExpansions {
+        /// The configured maximum number of entity expansions.
+        max_entity_expansions: u64,
+    },
+    /// Expanding entities produced more than `max_entity_bytes` bytes.
+    ExpansionTooLarge {
+        /// The configured maximum size of expanded entities.
+        max_entity_bytes: u64,
+    },
+    /// Elements were nested more than `max_depth` levels deep.
+    TooDeep {
+        /// The configured maximum depth.
+        max_depth: usize,
+    },
+    /// An element held more than `max_attributes` attributes.
+    TooManyAttributes {
+        /// The configured maximum number of attributes.
+        max_attributes: usize,
+    },
+    /// A text node was longer than `max_text_len` bytes.
+    TextTooLong {
+        /// The configured maximum text length.
+        max_text_len: usize,
+    },
+    /// The document referred to an external DTD or declared an external entity, with [`XmlLimits::deny_external_entities`].
+    ExternalEntity,
+    /// The document is not well-formed XML, e.g. because it was cut short or an entity refers to itself.
+    Malformed,
+}
+
+/// Structured error raised by an [`XmlReader`], locating the offending markup or text by line and column.
+///
+/// Retrieve it from a [`LimitReaderError`] of kind [`ErrorKind::XmlLimitExceeded`], or [`ErrorKind::Deserialize`] for [`XmlViolation::Malformed`], via `LimitReaderError::get_ref()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlLimitError {
+    line: u64,
+    col: u64,
+    violation: XmlViolation,
+}
+
+impl XmlLimitError {
+    /// Line of the offending markup or text in the decoded stream, starting at 1.
+    #[must_use]
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// Column of the offending markup or text in the decoded stream, in bytes starting at 1.
+    #[must_use]
+    pub fn col(&self) -> u64 {
+        self.col
+    }
+
+    /// The limit that was violated.
+    #[must_use]
+    pub fn violation(&self) -> XmlViolation {
+        self.violation
+    }
+}
+
+impl Display for XmlLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "markup at line {} column {} ", self.line, self.col)?;
+        match self.violation {
+            XmlViolation::TooManyExpansions {
+                max_entity_expansions,
+            } => write!(
+                f,
+                "exceeds the maximum of {max_entity_expansions} entity expansions"
+            ),
+            XmlViolation::ExpansionTooLarge { max_entity_bytes } => write!(
+                f,
+                "exceeds the maximum of {max_entity_bytes} bytes of expanded entities"
+            ),
+            XmlViolation::TooDeep { max_depth } => {
+                write!(f, "exceeds the maximum depth of {max_depth}")
+            }
+            XmlViolation::TooManyAttributes { max_attributes } => {
+                write!(f, "exceeds the maximum of {max_attributes} attributes")
+            }
+            XmlViolation::TextTooLong { max_text_len } => {
+                write!(f, "exceeds the maximum text length of {max_text_len} bytes")
+            }
+            XmlViolation::ExternalEntity => f.write_str("refers to an external entity"),
+            XmlViolation::Malformed => f.write_str("is malformed"),
+        }
+    }
+}
+
+impl std::error::Error for XmlLimitError {}
+
+impl LimitReader {
+    /// Pull XML events from `source`, decoded as configured and read through a [`crate::LimitReaderFallible`], enforcing `limits` on the document; see [`XmlReader`].  Digests, tees and observers do not apply, while [`LimitReader::max_ratio`] and [`LimitReader::min_size`] are enforced as the source is read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be decoded.  Errors reading the document are returned by [`XmlReader::read_event_into`].
+    ///
+    pub fn read_xml<'a>(
+        &self,
+        source: impl Read + 'a,
+        limits: &XmlLimits,
+    ) -> Result<XmlReader<LimitReaderFallible<Box<dyn Read + 'a>>>> {
+        Ok(XmlReader::new(self.strict_reader(source)?, limits))
+    }
+}
+
+/// Pulls the events of an XML document, enforcing [`XmlLimits`] on it.
+///
+/// References to entities declared in the internal subset of the document type declaration are expanded into [`Event::Text`], in text and in attribute values; external entities are never resolved.  Entities whose replacement text holds markup are rejected as malformed.  Each event is buffered whole before it is checked, so the size of the source should be limited as well.
+pub struct XmlReader<R> {
+    reader: Reader<Lines<BufReader<R>>>,
+    limits: XmlLimits,
+    // Replacement text of the internal general entities, by name.
+    entities: HashMap<Vec<u8>, Vec<u8>>,
+    depth: usize,
+    text_len: usize,
+    expansions: u64,
+    expanded_bytes: u64,
+}
+
+impl<R: Read> XmlReader<R> {
+    /// Wrap `reader`, enforcing `limits` on the document read from it.
+    pub fn new(reader: R, limits: &XmlLimits) -> Self {
+        Self {
+            reader: Reader::from_reader(Lines::new(BufReader::new(reader))),
+            limits: limits.clone(),
+            entities: HashMap::new(),
+            depth: 0,
+            text_len: 0,
+            expansions: 0,
+            expanded_bytes: 0,
+        }
+    }
+
+    /// Read the next event, using `buf` as its buffer, and returns [`Event::Eof`] at the end of the document.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::XmlLimitExceeded`] or [`ErrorKind::Deserialize`] if the document violates a limit or is malformed, or the error of the underlying reader.
+    ///
+    pub fn read_event_into<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let offset = self.reader.buffer_position();
+        // Forget the newlines before the event, which are never located again.
+        self.reader.get_mut().locate(offset);
+        let event = match self.reader.read_event_into(buf) {
+            Ok(event) => event,
+            Err(quick_xml::Error::Io(err)) => {
+                return Err(match Arc::try_unwrap(err) {
+                    Ok(err) => LimitReaderError::from_io(err, ErrorKind::ReadError),
+                    Err(err) => LimitReaderError::new(ErrorKind::ReadError, err.to_string()),
+                });
+            }
+            Err(_) => {
+                let offset = self.reader.error_position();
+                return Err(self.violation(offset, XmlViolation::Malformed));
+            }
+        };
+
+        self.check(event)
+            .map_err(|violation| self.violation(offset, violation))
+    }
+
+    /// Read the rest of the document, only to validate it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`XmlReader::read_event_into`].
+    pub fn validate(mut self) -> Result<()> {
+        let mut buf = Vec::new();
+        while self.read_event_into(&mut buf)? != Event::Eof {
+            buf.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Unwrap the underlying reader, dropping any bytes buffered but not yet parsed.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner().inner.into_inner()
+    }
+
+    fn check<'b>(&mut self, event: Event<'b>) -> std::result::Result<Event<'b>, XmlViolation> {
+        match event {
+            Event::Start(start) => {
+                self.text_len = 0;
+                self.depth += 1;
+                self.check_depth(self.depth)?;
+
+                Ok(Event::Start(self.attributes(start)?))
+            }
+            Event::Empty(start) => {
+                self.text_len = 0;
+                self.check_depth(self.depth + 1)?;
+
+                Ok(Event::Empty(self.attributes(start)?))
+            }
+            Event::End(end) => {
+                self.text_len = 0;
+                self.depth = self.depth.saturating_sub(1);
+
+                Ok(Event::End(end))
+            }
+            Event::Text(text) => {
+                self.add_text(text.len())?;
+
+                Ok(Event::Text(text))
+            }
+            Event::CData(data) => {
+                self.add_text(data.len())?;
+
+                Ok(Event::CData(data))
+            }
+            Event::GeneralRef(reference) if self.entities.contains_key(&*reference) => {
+                let mut out = Vec::new();
+                expand(&mut self.expansion(), &reference, &mut out, &mut Vec::new())?;
+                self.add_text(out.len())?;
+                // Markup was rejected while expanding, so the text is valid UTF-8 and escaped as in the document.
+                let text = String::from_utf8(out).map_err(|_| XmlViolation::Malformed)?;
+
+                Ok(Event::Text(BytesText::from_escaped(text)))
+            }
+            Event::GeneralRef(reference) => {
+                self.add_text(reference.len() + 2)?;
+
+                Ok(Event::GeneralRef(reference))
+            }
+            Event::DocType(doctype) => {
+                self.text_len = 0;
+                self.declare(&doctype)?;
+
+                Ok(Event::DocType(doctype))
+            }
+            event => {
+                self.text_len = 0;
+
+                Ok(event)
+            }
+        }
+    }
+
+    fn check_depth(&self, depth: usize) -> std::result::Result<(), XmlViolation> {
+        match self.limits.max_depth {
+            Some(max_depth) if depth > max_depth => Err(XmlViolation::TooDeep { max_depth }),
+            _ => Ok(()),
+        }
+    }
+
+    fn add_text(&mut self, len: usize) -> std::result::Result<(), XmlViolation> {
+        self.text_len = self.text_len.saturating_add(len);
+        match self.limits.max_text_len {
+            Some(max_text_len) if self.text_len > max_text_len => {
+                Err(XmlViolation::TextTooLong { max_text_len })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Counts the attributes of `start`, rebuilding it if a value refers to a declared entity.
+    fn attributes<'b>(
+        &mut self,
+        start: BytesStart<'b>,
+    ) -> std::result::Result<BytesStart<'b>, XmlViolation> {
+        let mut expanded = Vec::new();
+        let mut changed = false;
+        for (count, attr) in start.attributes().enumerate() {
+            if let Some(max_attributes) = self.limits.max_attributes {
+                if count >= max_attributes {
+                    return Err(XmlViolation::TooManyAttributes { max_attributes });
+                }
+            }
+            let attr = attr.map_err(|_| XmlViolation::Malformed)?;
+            if references(&attr.value).any(|name| self.entities.contains_key(name)) {
+                let mut value = Vec::new();
+                expand_text(
+                    &mut self.expansion(),
+                    &attr.value,
+                    &mut value,
+                    &mut Vec::new(),
+                )?;
+                expanded.push((attr.key.as_ref().to_vec(), value));
+                changed = true;
+            } else {
+                expanded.push((attr.key.as_ref().to_vec(), attr.value.into_owned()));
+            }
+        }
+        if !changed {
+            return Ok(start);
+        }
+
+        let name = std::str::from_utf8(start.name().as_ref())
+            .map_err(|_| XmlViolation::Malformed)?
+            .to_owned();
+        let mut rebuilt = BytesStart::new(name);
+        for (key, value) in &expanded {
+            rebuilt.push_attribute(Attribute {
+                key: quick_xml::name::QName(key),
+                value: Cow::Borrowed(value),
+            });
+        }
+
+        Ok(rebuilt)
+    }
+
+    // Declares the internal general entities of a document type declaration, as read by quick-xml without `<!DOCTYPE` and `>`.
+    fn declare(&mut self, doctype: &[u8]) -> std::result::Result<(), XmlViolation> {
+        let external_id = doctype
+            .split(u8::is_ascii_whitespace)
+            .filter(|token| !token.is_empty())
+            .nth(1);
+        if self.limits.deny_external_entities && matches!(external_id, Some(b"SYSTEM" | b"PUBLIC"))
+        {
+            return Err(XmlViolation::ExternalEntity);
+        }
+
+        let mut i = 0;
+        while i < doctype.len() {
+            let rest = &doctype[i..];
+            i = match rest[0] {
+                quote @ (b'"' | b'\'') => i + quoted(rest, quote)?.len() + 2,
+                b'<' if rest.starts_with(b"<!--") => {
+                    let end = find(&rest[4..], b"-->").ok_or(XmlViolation::Malformed)?;
+                    i + 4 + end + 3
+                }
+                b'<' if rest.starts_with(b"<!ENTITY") => i + 8 + self.entity(&rest[8..])?,
+                _ => i + 1,
+            };
+        }
+
+        Ok(())
+    }
+
+    // Parses an entity declaration following `<!ENTITY`, returning the length parsed, up to the end of the replacement text if any.
+    fn entity(&mut self, decl: &[u8]) -> std::result::Result<usize, XmlViolation> {
+        let mut i = skip_whitespace(decl, 0);
+        let parameter = decl.get(i) == Some(&b'%');
+        if parameter {
+            i = skip_whitespace(decl, i + 1);
+        }
+        let len = decl[i..]
+            .iter()
+            .position(|&b| b.is_ascii_whitespace() || b == b'"' || b == b'\'' || b == b'>')
+            .unwrap_or(decl.len() - i);
+        let name = &decl[i..i + len];
+        i = skip_whitespace(decl, i + len);
+
+        match decl.get(i) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let value = quoted(&decl[i..], quote)?;
+                // The first declaration of an entity is binding.
+                if !parameter && !self.entities.contains_key(name) {
+                    self.entities.insert(name.to_vec(), value.to_vec());
+                }
+
+                Ok(i + value.len() + 2)
+            }
+            _ if self.limits.deny_external_entities => Err(XmlViolation::ExternalEntity),
+            _ => Ok(i),
+        }
+    }
+
+    fn expansion(&mut self) -> Expansion<'_> {
+        Expansion {
+            entities: &self.entities,
+            limits: &self.limits,
+            expansions: &mut self.expansions,
+            bytes: &mut self.expanded_bytes,
+        }
+    }
+
+    fn violation(&mut self, offset: u64, violation: XmlViolation) -> LimitReaderError {
+        let (line, col) = self.reader.get_mut().locate(offset);
+        let kind = match violation {
+            XmlViolation::Malformed => ErrorKind::Deserialize,
+            _ => ErrorKind::XmlLimitExceeded,
+        };
+
+        LimitReaderError::new(
+            kind,
+            XmlLimitError {
+                line,
+                col,
+                violation,
+            },
+        )
+    }
+}
+
+// The declared entities, with the expansions counted against the limits so far.
+struct Expansion<'a> {
+    entities: &'a HashMap<Vec<u8>, Vec<u8>>,
+    limits: &'a XmlLimits,
+    expansions: &'a mut u64,
+    bytes: &'a mut u64,
+}
+
+// Appends the replacement text of the declared entity `name` to `out`, expanding nested references; `open` holds the entities being expanded.
+fn expand<'a>(
+    expansion: &mut Expansion<'a>,
+    name: &[u8],
+    out: &mut Vec<u8>,
+    open: &mut Vec<&'a [u8]>,
+) -> std::result::Result<(), XmlViolation> {
+    let entities = expansion.entities;
+    let Some((name, value)) = entities.get_key_value(name) else {
+        return Err(XmlViolation::Malformed);
+    };
+    if open.contains(&name.as_slice()) || value.contains(&b'<') {
+        return Err(XmlViolation::Malformed);
+    }
+    *expansion.expansions += 1;
+    let max_entity_expansions = expansion.limits.max_entity_expansions;
+    if *expansion.expansions > max_entity_expansions {
+        return Err(XmlViolation::TooManyExpansions {
+            max_entity_expansions,
+        });
+    }
+
+    open.push(name);
+    expand_text(expansion, value, out, open)?;
+    open.pop();
+
+    Ok(())
+}
+
+// Appends `text` to `out`, expanding references to declared entities and keeping any other reference as is.
+fn expand_text<'a>(
+    expansion: &mut Expansion<'a>,
+    text: &[u8],
+    out: &mut Vec<u8>,
+    open: &mut Vec<&'a [u8]>,
+) -> std::result::Result<(), XmlViolation> {
+    let mut rest = text;
+    while let Some(amp) = rest.iter().position(|&b| b == b'&') {
+        let len = rest[amp..]
+            .iter()
+            .position(|&b| b == b';')
+            .ok_or(XmlViolation::Malformed)?;
+        let name = &rest[amp + 1..amp + len];
+        if expansion.entities.contains_key(name) {
+            append(expansion, &rest[..amp], out, open)?;
+            expand(expansion, name, out, open)?;
+        } else {
+            append(expansion, &rest[..=amp + len], out, open)?;
+        }
+        rest = &rest[amp + len + 1..];
+    }
+
+    append(expansion, rest, out, open)
+}
+
+// Appends `bytes` to `out`, counting them against `max_entity_bytes` if they are part of an entity.
+fn append(
+    expansion: &mut Expansion<'_>,
+    bytes: &[u8],
+    out: &mut Vec<u8>,
+    open: &[&[u8]],
+) -> std::result::Result<(), XmlViolation> {
+    if !open.is_empty() {
+        *expansion.bytes = expansion.bytes.saturating_add(bytes.len() as u64);
+        let max_entity_bytes = expansion.limits.max_entity_bytes;
+        if *expansion.bytes > max_entity_bytes {
+            return Err(XmlViolation::ExpansionTooLarge { max_entity_bytes });
+        }
+    }
+    out.extend_from_slice(bytes);
+
+    Ok(())
+}
+
+// The names of the references to general entities in `text`, other than character references.
+fn references(text: &[u8]) -> impl Iterator<Item = &[u8]> {
+    text.split(|&b| b == b'&').skip(1).filter_map(|rest| {
+        let len = rest.iter().position(|&b| b == b';')?;
+
+        Some(&rest[..len]).filter(|name| !name.starts_with(b"#"))
+    })
+}
+
+// The content of the literal quoted by `quote` at the start of `literal`.
+fn quoted(literal: &[u8], quote: u8) -> std::result::Result<&[u8], XmlViolation> {
+    let len = literal[1..]
+        .iter()
+        .position(|&b| b == quote)
+        .ok_or(XmlViolation::Malformed)?;
+
+    Ok(&literal[1..=len])
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn skip_whitespace(bytes: &[u8], i: usize) -> usize {
+    bytes[i..]
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .map_or(bytes.len(), |len| i + len)
+}
+
+// Tracks the newlines of the bytes consumed from `inner`, to locate offsets in the stream by line and column.
+struct Lines<R> {
+    inner: R,
+    consumed: u64,
+    // Offsets of the newlines consumed but not yet located past.
+    newlines: VecDeque<u64>,
+    line: u64,
+    line_start: u64,
+}
+
+impl<R: BufRead> Lines<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            consumed: 0,
+            newlines: VecDeque::new(),
+            line: 1,
+            line_start: 0,
+        }
+    }
+
+    // Locates `offset`, which must not precede the offset located last.
+    fn locate(&mut self, offset: u64) -> (u64, u64) {
+        while let Some(&newline) = self.newlines.front().filter(|&&nl| nl < offset) {
+            self.newlines.pop_front();
+            self.line += 1;
+            self.line_start = newline + 1;
+        }
+
+        (self.line, offset.saturating_sub(self.line_start) + 1)
+    }
+}
+
+impl<R: BufRead> Read for Lines<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Lines<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // The bytes to consume are still buffered, so this does not read.
+        if let Ok(buf) = self.inner.fill_buf() {
+            let consumed = self.consumed;
+            self.newlines.extend(
+                buf[..amt]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &b)| b == b'\n')
+                    .map(|(i, _)| consumed + i as u64),
+            );
+        }
+        self.consumed += amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAUGHS: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE lolz [
+  <!ENTITY lol "lol">
+  <!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+  <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+  <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+  <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+  <!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+  <!ENTITY lol6 "&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;">
+  <!ENTITY lol7 "&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;">
+  <!ENTITY lol8 "&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;">
+  <!ENTITY lol9 "&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;">
+]>
+<lolz>
+  &lol9;
+</lolz>
+"#;
+
+    fn violation(err: &LimitReaderError) -> &XmlLimitError {
+        err.get_ref().downcast_ref::<XmlLimitError>().unwrap()
+    }
+
+    #[test]
+    fn rejects_billion_laughs() {
+        let mut limits = XmlLimits::new();
+        limits.max_entity_expansions(10_000);
+        let err = LimitReader::new()
+            .read_xml(LAUGHS.as_bytes(), &limits)
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::XmlLimitExceeded);
+        let xml_err = violation(&err);
+        assert_eq!(
+            xml_err.violation(),
+            XmlViolation::TooManyExpansions {
+                max_entity_expansions: 10_000
+            }
+        );
+        assert_eq!((xml_err.line(), xml_err.col()), (15, 3));
+
+        let mut limits = XmlLimits::new();
+        limits
+            .max_entity_expansions(u64::MAX)
+            .max_entity_bytes(1 << 20);
+        let err = LimitReader::new()
+            .read_xml(LAUGHS.as_bytes(), &limits)
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            violation(&err).violation(),
+            XmlViolation::ExpansionTooLarge {
+                max_entity_bytes: 1 << 20
+            }
+        );
+    }
+
+    #[test]
+    fn limits_expansion_by_default() {
+        let err = LimitReader::new()
+            .read_xml(LAUGHS.as_bytes(), &XmlLimits::new())
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            violation(&err).violation(),
+            XmlViolation::TooManyExpansions {
+                max_entity_expansions: 10_000
+            }
+        );
+    }
+
+    #[test]
+    fn forgets_newlines_of_parsed_events() {
+        let text = format!("<a>{}</a>", "<b/>\n".repeat(10_000));
+        let mut reader = LimitReader::new()
+            .limit(1 << 20)
+            .read_xml(text.as_bytes(), &XmlLimits::new())
+            .unwrap();
+        let mut buf = Vec::new();
+        while reader.read_event_into(&mut buf).unwrap() != Event::Eof {
+            assert!(reader.reader.get_ref().newlines.len() < 100);
+            buf.clear();
+        }
+    }
+
+    #[test]
+    fn expands_entities_within_limits() {
+        let text = r#"<!DOCTYPE doc [<!ENTITY who "Mike &amp; co"><!ENTITY greeting "Hi &who;">]>
+<doc by="&who;">&greeting;!</doc>"#;
+        let mut limits = XmlLimits::new();
+        limits
+            .max_entity_expansions(3)
+            .max_entity_bytes(29)
+            .max_text_len(17);
+        let mut reader = LimitReader::new()
+            .read_xml(text.as_bytes(), &limits)
+            .unwrap();
+
+        let mut buf = Vec::new();
+        let mut texts = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf).unwrap() {
+                Event::Start(start) => {
+                    let by = start.try_get_attribute("by").unwrap().unwrap();
+                    assert_eq!(by.unescape_value().unwrap(), "Mike & co");
+                }
+                Event::Text(text) => texts.push(text.decode().unwrap().into_owned()),
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        assert_eq!(texts, ["\n", "Hi Mike &amp; co", "!"]);
+
+        limits.max_entity_expansions(2);
+        let err = LimitReader::new()
+            .read_xml(text.as_bytes(), &limits)
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        let xml_err = violation(&err);
+        assert_eq!((xml_err.line(), xml_err.col()), (2, 17));
+    }
+
+    #[test]
+    fn enforces_structural_limits() {
+        let check = |text: &str, limits: &XmlLimits| {
+            let err = LimitReader::new()
+                .read_xml(text.as_bytes(), limits)
+                .unwrap()
+                .validate()
+                .unwrap_err();
+            let xml_err = violation(&err);
+
+            (
+                err.kind(),
+                xml_err.violation(),
+                xml_err.line(),
+                xml_err.col(),
+            )
+        };
+        let mut limits = XmlLimits::new();
+        limits.max_depth(2).max_attributes(2).max_text_len(4);
+
+        assert_eq!(
+            check("<a><b>\n  <c/></b></a>", &limits),
+            (
+                ErrorKind::XmlLimitExceeded,
+                XmlViolation::TooDeep { max_depth: 2 },
+                2,
+                3
+            )
+        );
+        assert_eq!(
+            check(r#"<a x="1" y="2" z="3"/>"#, &limits),
+            (
+                ErrorKind::XmlLimitExceeded,
+                XmlViolation::TooManyAttributes { max_attributes: 2 },
+                1,
+                1
+            )
+        );
+        assert_eq!(
+            check("<a>ab<![CDATA[cde]]></a>", &limits).1,
+            XmlViolation::TextTooLong { max_text_len: 4 }
+        );
+        assert_eq!(check("<a><b></a>", &limits).0, ErrorKind::Deserialize);
+        assert_eq!(
+            check(r#"<!DOCTYPE a [<!ENTITY e "&e;">]><a>&e;</a>"#, &limits).1,
+            XmlViolation::Malformed
+        );
+
+        let mut limits = XmlLimits::new();
+        limits.deny_external_entities();
+        assert_eq!(
+            check(r#"<!DOCTYPE a SYSTEM "a.dtd"><a/>"#, &limits).1,
+            XmlViolation::ExternalEntity
+        );
+        assert_eq!(
+            check(
+                r#"<!DOCTYPE a [<!ENTITY e SYSTEM "file:///etc/passwd">]><a>&e;</a>"#,
+                &limits
+            )
+            .1,
+            XmlViolation::ExternalEntity
+        );
+        LimitReader::new()
+            .read_xml(&b"<!DOCTYPE a [<!ENTITY e \"x\">]><a>&e;</a>"[..], &limits)
+            .unwrap()
+            .validate()
+            .unwrap();
+    }
+}
@@ -0,0 +1,341 @@
+use crate::error::{ErrorKind, LimitReaderError};
+use crate::{LimitReader, LimitReaderResult as Result};
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::Read;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Limits on the expanded size of YAML documents loaded by [`LimitReader::load_yaml`].
+///
+/// Aliases are expanded into copies of the node they refer to, so a few hundred bytes of nested anchors can expand into billions of nodes.  Both limits count the document as expanded, across all documents of the stream.  By default, a stream may expand 10,000 aliases into 1,000,000 nodes.
+#[derive(Debug, Clone)]
+pub struct YamlLimits {
+    max_aliases: u64,
+    max_nodes: u64,
+}
+
+impl Default for YamlLimits {
+    fn default() -> Self {
+        Self {
+            max_aliases: 10_000,
+            max_nodes: 1_000_000,
+        }
+    }
+}
+
+impl YamlLimits {
+    /// Create a new [`YamlLimits`] with the default limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the stream to `max_aliases` alias expansions, counting an alias again each time the node holding it is expanded.
+    pub fn max_aliases(&mut self, max_aliases: u64) -> &mut Self {
+        self.max_aliases = max_aliases;
+
+        self
+    }
+
+    /// Limit the stream to `max_nodes` scalars, sequences and mappings once aliases are expanded, counting mapping keys as nodes.
+    pub fn max_nodes(&mut self, max_nodes: u64) -> &mut Self {
+        self.max_nodes = max_nodes;
+
+        self
+    }
+}
+
+/// The limit violated by a YAML document; see [`YamlLimitError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum YamlViolation {
+    /// The stream expanded more than `max_aliases` aliases.
+    TooManyAliases {
+        /// The configured maximum number of alias expansions.
+        max_aliases: u64,
+    },
+    /// The stream expanded into more than `max_nodes` nodes.
+    TooManyNodes {
+        /// The configured maximum number of nodes.
+        max_nodes: u64,
+    },
+}
+
+/// Structured error raised by [`LimitReader::load_yaml`], locating the node at which the expanded document exceeded its [`YamlLimits`].
+///
+/// Retrieve it from a [`LimitReaderError`] of kind [`ErrorKind::YamlLimitExceeded`] via `LimitReaderError::get_ref()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YamlLimitError {
+    line: usize,
+    col: usize,
+    violation: YamlViolation,
+}
+
+impl YamlLimitError {
+    /// Line of the offending node in the source, starting at 1.
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Column of the offending node in the source, as reported by the YAML parser.
+    #[must_use]
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    /// The limit that was violated.
+    #[must_use]
+    pub fn violation(&self) -> YamlViolation {
+        self.violation
+    }
+}
+
+impl Display for YamlLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "node at line {} column {} ", self.line, self.col)?;
+        match self.violation {
+            YamlViolation::TooManyAliases { max_aliases } => {
+                write!(f, "exceeds the maximum of {max_aliases} alias expansions")
+            }
+            YamlViolation::TooManyNodes { max_nodes } => {
+                write!(f, "exceeds the maximum of {max_nodes} expanded nodes")
+            }
+        }
+    }
+}
+
+impl std::error::Error for YamlLimitError {}
+
+impl LimitReader {
+    /// Load the YAML documents of `source`, decoded as configured and read through a [`crate::LimitReaderFallible`], rejecting streams that expand beyond `limits`.
+    ///
+    /// The expanded size of each anchored node is tracked as the source is parsed, so an alias is counted without copying the node it refers to, and no node is built once a limit is exceeded.  Digests, tees and observers do not apply, while [`LimitReader::max_ratio`] and [`LimitReader::min_size`] are enforced as the source is read.
+    ///
+    /// # Errors
+    ///
+    /// Once the source exceeds the limit, this returns a [`LimitReaderError`] of the kind [`ErrorKind::LimitExceeded`].  A stream expanding beyond `limits` returns [`ErrorKind::YamlLimitExceeded`] with a [`YamlLimitError`], invalid UTF-8 returns [`ErrorKind::Utf8Error`], and malformed YAML returns [`ErrorKind::Deserialize`].
+    ///
+    pub fn load_yaml(&self, source: impl Read, limits: &YamlLimits) -> Result<Vec<Yaml>> {
+        let mut bytes = Vec::new();
+        self.strict_reader(source)?
+            .read_to_end(&mut bytes)
+            .map_err(|err| LimitReaderError::from_io(err, ErrorKind::ReadError))?;
+        let text = String::from_utf8(bytes)?;
+
+        let mut receiver = Receiver::new(limits);
+        Parser::new_from_str(&text)
+            .load(&mut receiver, true)
+            .map_err(|err| LimitReaderError::new(ErrorKind::Deserialize, err))?;
+
+        receiver.finish()
+    }
+}
+
+// Counts the expanded nodes of the stream, forwarding events to a `YamlLoader` until a limit is exceeded.
+struct Receiver<'a> {
+    limits: &'a YamlLimits,
+    loader: YamlLoader,
+    // Expanded node and alias counts of each anchored node seen so far, by anchor id.
+    sizes: HashMap<usize, (u64, u64)>,
+    // Anchor id of each open sequence or mapping, with the node and alias counts before it.
+    open: Vec<(usize, u64, u64)>,
+    aliases: u64,
+    nodes: u64,
+    error: Option<YamlLimitError>,
+}
+
+impl<'a> Receiver<'a> {
+    fn new(limits: &'a YamlLimits) -> Self {
+        Self {
+            limits,
+            loader: YamlLoader::default(),
+            sizes: HashMap::new(),
+            open: Vec::new(),
+            aliases: 0,
+            nodes: 0,
+            error: None,
+        }
+    }
+
+    fn count(&mut self, ev: &Event) -> std::result::Result<(), YamlViolation> {
+        match *ev {
+            Event::Scalar(_, _, anchor, _) => {
+                self.add_nodes(1)?;
+                if anchor > 0 {
+                    self.sizes.insert(anchor, (1, 0));
+                }
+            }
+            Event::SequenceStart(anchor, _) | Event::MappingStart(anchor, _) => {
+                self.open.push((anchor, self.nodes, self.aliases));
+                self.add_nodes(1)?;
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                if let Some((anchor, nodes, aliases)) = self.open.pop() {
+                    if anchor > 0 {
+                        self.sizes
+                            .insert(anchor, (self.nodes - nodes, self.aliases - aliases));
+                    }
+                }
+            }
+            Event::Alias(anchor) => {
+                // The loader turns an unknown anchor into a single bad value.
+                let (nodes, aliases) = self.sizes.get(&anchor).copied().unwrap_or((1, 0));
+                self.aliases = self.aliases.saturating_add(aliases).saturating_add(1);
+                let max_aliases = self.limits.max_aliases;
+                if self.aliases > max_aliases {
+                    return Err(YamlViolation::TooManyAliases { max_aliases });
+                }
+                self.add_nodes(nodes)?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn add_nodes(&mut self, n: u64) -> std::result::Result<(), YamlViolation> {
+        self.nodes = self.nodes.saturating_add(n);
+        let max_nodes = self.limits.max_nodes;
+        if self.nodes > max_nodes {
+            return Err(YamlViolation::TooManyNodes { max_nodes });
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<Yaml>> {
+        if let Some(err) = self.error {
+            return Err(LimitReaderError::new(ErrorKind::YamlLimitExceeded, err));
+        }
+
+        Ok(self.loader.documents().to_vec())
+    }
+}
+
+impl MarkedEventReceiver for Receiver<'_> {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        if self.error.is_some() {
+            return;
+        }
+        match self.count(&ev) {
+            Ok(()) => self.loader.on_event(ev, mark),
+            Err(violation) => {
+                self.error = Some(YamlLimitError {
+                    line: mark.line(),
+                    col: mark.col(),
+                    violation,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAUGHS: &str = "\
+a: &a [lol, lol, lol, lol, lol, lol, lol, lol, lol]
+b: &b [*a, *a, *a, *a, *a, *a, *a, *a, *a]
+c: &c [*b, *b, *b, *b, *b, *b, *b, *b, *b]
+d: &d [*c, *c, *c, *c, *c, *c, *c, *c, *c]
+e: &e [*d, *d, *d, *d, *d, *d, *d, *d, *d]
+f: &f [*e, *e, *e, *e, *e, *e, *e, *e, *e]
+g: &g [*f, *f, *f, *f, *f, *f, *f, *f, *f]
+h: &h [*g, *g, *g, *g, *g, *g, *g, *g, *g]
+i: &i [*h, *h, *h, *h, *h, *h, *h, *h, *h]
+";
+
+    #[test]
+    fn rejects_billion_laughs() {
+        let mut limits = YamlLimits::new();
+        limits.max_nodes(100_000).max_aliases(u64::MAX);
+        let err = LimitReader::new()
+            .load_yaml(LAUGHS.as_bytes(), &limits)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::YamlLimitExceeded);
+        let yaml_err = err.get_ref().downcast_ref::<YamlLimitError>().unwrap();
+        assert_eq!(
+            yaml_err.violation(),
+            YamlViolation::TooManyNodes { max_nodes: 100_000 }
+        );
+        assert_eq!(yaml_err.line(), 6);
+
+        let mut limits = YamlLimits::new();
+        limits.max_aliases(20);
+        let err = LimitReader::new()
+            .load_yaml(LAUGHS.as_bytes(), &limits)
+            .unwrap_err();
+        let yaml_err = err.get_ref().downcast_ref::<YamlLimitError>().unwrap();
+        assert_eq!(
+            yaml_err.violation(),
+            YamlViolation::TooManyAliases { max_aliases: 20 }
+        );
+        assert_eq!(yaml_err.line(), 3);
+    }
+
+    #[test]
+    fn limits_expansion_by_default() {
+        let err = LimitReader::new()
+            .load_yaml(LAUGHS.as_bytes(), &YamlLimits::new())
+            .unwrap_err();
+        let yaml_err = err.get_ref().downcast_ref::<YamlLimitError>().unwrap();
+        assert_eq!(
+            yaml_err.violation(),
+            YamlViolation::TooManyAliases {
+                max_aliases: 10_000
+            }
+        );
+
+        let mut limits = YamlLimits::new();
+        limits.max_aliases(u64::MAX);
+        let err = LimitReader::new()
+            .load_yaml(LAUGHS.as_bytes(), &limits)
+            .unwrap_err();
+        let yaml_err = err.get_ref().downcast_ref::<YamlLimitError>().unwrap();
+        assert_eq!(
+            yaml_err.violation(),
+            YamlViolation::TooManyNodes {
+                max_nodes: 1_000_000
+            }
+        );
+    }
+
+    #[test]
+    fn loads_documents_within_limits() {
+        let text = "base: &base {retries: 3, verbose: true}\nprod: *base\n---\n[1, 2]\n";
+        let mut limits = YamlLimits::new();
+        // The root mapping and its 2 keys, the base mapping of 5 nodes twice, then a sequence of 3 nodes.
+        limits.max_nodes(3 + 5 + 5 + 3).max_aliases(1);
+        let docs = LimitReader::new()
+            .load_yaml(text.as_bytes(), &limits)
+            .unwrap();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0]["prod"]["retries"].as_i64(), Some(3));
+        assert_eq!(docs[1][1].as_i64(), Some(2));
+
+        limits.max_nodes(3 + 5 + 5 + 2);
+        let err = LimitReader::new()
+            .load_yaml(text.as_bytes(), &limits)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::YamlLimitExceeded);
+    }
+
+    #[test]
+    fn reports_malformed_and_oversized_sources() {
+        let limits = YamlLimits::new();
+        let err = LimitReader::new()
+            .load_yaml(&b"a: [1, 2"[..], &limits)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Deserialize);
+
+        let err = LimitReader::new()
+            .limit(16)
+            .load_yaml(LAUGHS.as_bytes(), &limits)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+    }
+}